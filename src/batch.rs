@@ -0,0 +1,365 @@
+use std::io::Read;
+use std::time::Duration;
+use crate::error::*;
+use crate::{vffile, vfgen, vfjson, vflevel, vfsearch, vfsim, vfstrategy};
+use crate::vflip::*;
+
+// exit codes
+pub const EXIT_SOLVED: i32 = 0;
+pub const EXIT_UNSOLVABLE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+
+// the options of a batch solve
+struct Options {
+  puzzle: Puzzle,
+  level: Option<u8>,
+  strategy: Box<dyn vfstrategy::Strategy>,
+  json: bool
+}
+
+// the options of a simulation
+struct Simulation {
+  levels: Vec<u8>,
+  rounds: u64,
+  seed: u64,
+  strategy: Box<dyn vfstrategy::Strategy>
+}
+
+// runs a batch solve on the arguments after the mode, returning the exit code; `solve [--size rows columns max]
+// [--headers numbers] [--board rows] [--level n|off] [--budget depth ms] [--strategy name] [--json] [file]`
+pub fn run(args: &[String]) -> i32
+{
+  let (code, print_string) = solve(args, &mut std::io::stdin());
+  print!("{}", print_string);
+  code
+}
+
+// solves the puzzle of the arguments, reading it from the input if there's no file or headers,
+// returning the exit code and what to print
+fn solve(args: &[String], input: &mut dyn Read) -> (i32, String)
+{
+  let options = match parse(args, input) {
+    Ok(options) => options,
+    Err(error) =>
+    {
+      eprintln!("vflip solve: {}", error);
+      return (EXIT_USAGE, String::new());
+    }
+  };
+  let Options { puzzle, level, strategy, json } = options;
+
+  // the whole result at once for other tools
+  let aggregate = aggregate(&puzzle, level);
+  let solvable = aggregate.num_solutions > 0 && aggregate.total_weight > 0.0;
+  if json
+  {
+    let best = if solvable { vfstrategy::best(strategy.as_ref(), &puzzle, level, &aggregate) } else { None };
+    let print_string = format!("{}\n", vfjson::write(&puzzle, level, &aggregate, best));
+    return (if solvable { EXIT_SOLVED } else { EXIT_UNSOLVABLE }, print_string);
+  }
+
+  // the board with what is certain, and the chance each cell is a voltorb
+  let mut print_string = format!("{}\n\n", print_with_headers(&puzzle, Some(&aggregate)));
+  print_string.push_str(&print_probabilities(&aggregate, &puzzle.board, 0));
+  print_string.push_str(&format!("{} solutions\n", aggregate.num_solutions));
+  if aggregate.num_solutions == 0 {
+    return (EXIT_UNSOLVABLE, print_string);
+  }
+  if aggregate.total_weight <= 0.0 {
+    print_string.push_str(&format!("no solution could be dealt on level {}\n", level.unwrap_or(0)));
+    return (EXIT_UNSOLVABLE, print_string);
+  }
+
+  // the recommended flip, by column and row like the commands
+  match vfstrategy::best(strategy.as_ref(), &puzzle, level, &aggregate) {
+    Some((row, column)) => { print_string.push_str(&format!("flip {} {}\n", column+1, row+1)); }
+    None => { print_string.push_str("no flip left\n"); }
+  }
+
+  (EXIT_SOLVED, print_string)
+}
+
+// deals a round on the arguments after the mode, returning the exit code; `deal level [--seed n] [--reveal]`
+pub fn deal(args: &[String]) -> i32
+{
+  let round = match parse_deal(args) {
+    Ok(round) => round,
+    Err(error) =>
+    {
+      eprintln!("vflip deal: {}", error);
+      return EXIT_USAGE;
+    }
+  };
+  let (round, reveal) = round;
+
+  // the seed first, so the round can be dealt again
+  println!("# level {} seed {}", round.level, round.seed);
+  print!("{}", vffile::write(&round.puzzle, Some(round.level)));
+  if reveal {
+    for line in print(&round.solution).lines() {
+      println!("#{}", line);
+    }
+  }
+  EXIT_SOLVED
+}
+
+// reads the level and options of a deal, and deals the round
+fn parse_deal(args: &[String]) -> Result<(vfgen::Round, bool)>
+{
+  let mut level: Option<u8> = None;
+  let mut seed: Option<u64> = None;
+  let mut reveal = false;
+
+  // take apart the arguments
+  let mut arg_iter = args.iter().map(|arg| arg.as_str());
+  while let Some(arg) = arg_iter.next()
+  {
+    match arg
+    {
+      "--seed" => { seed = Some(arg_iter.next().ok_or(Error::new("--seed needs a value"))?.parse::<u64>()?); }
+      "--reveal" => { reveal = true; }
+      _ if arg.starts_with("--") => { return Err(Error::new(format!("Unknown option {}", arg).as_str())); }
+      _ => { level = Some(arg.parse::<u8>()?); }
+    }
+  }
+
+  let level = level.ok_or(Error::new("No level given"))?;
+  Ok((vfgen::generate(level, seed)?, reveal))
+}
+
+// simulates rounds on the arguments after the mode, returning the exit code; `simulate [--rounds n]
+// [--level n|all] [--strategy name] [--seed n] [--budget depth ms]`
+pub fn simulate(args: &[String]) -> i32
+{
+  match parse_simulate(args).and_then(|Simulation { levels, rounds, seed, strategy }| {
+    for level in levels {
+      print!("{}", vfsim::print_report(&vfsim::simulate(level, rounds, seed, strategy.as_ref())?));
+    }
+    Ok(())
+  }) {
+    Ok(()) => EXIT_SOLVED,
+    Err(error) =>
+    {
+      eprintln!("vflip simulate: {}", error);
+      EXIT_USAGE
+    }
+  }
+}
+
+// reads the levels, rounds, seed and strategy of a simulation
+fn parse_simulate(args: &[String]) -> Result<Simulation>
+{
+  let mut levels: Vec<u8> = (1..=vflevel::NUM_LEVELS).collect();
+  let mut rounds = 1000;
+  let mut seed = 0;
+  let mut strategy = "greedy";
+  let mut budget = vfsearch::Budget { depth: 1, time: Duration::from_millis(10) };
+
+  // take apart the arguments
+  let mut arg_iter = args.iter().map(|arg| arg.as_str());
+  let mut next = |name: &str| arg_iter.next().ok_or(Error::new(format!("{} needs a value", name).as_str()));
+  loop
+  {
+    let arg = match next("") {
+      Ok(arg) => arg,
+      Err(_) => { break; }
+    };
+    match arg
+    {
+      "--rounds" => { rounds = next(arg)?.parse::<u64>()?; }
+      "--seed" => { seed = next(arg)?.parse::<u64>()?; }
+      "--strategy" => { strategy = next(arg)?; }
+      "--level" =>
+      {
+        levels = match next(arg)? {
+          "all" => levels,
+          value => vec![value.parse::<u8>()?]
+        };
+      }
+      "--budget" =>
+      {
+        let depth = next(arg)?.parse::<usize>()?;
+        let time = next(arg)?.parse::<u64>()?;
+        budget = vfsearch::Budget { depth, time: Duration::from_millis(time) };
+      }
+      _ => { return Err(Error::new(format!("Unknown option {}", arg).as_str())); }
+    }
+  }
+
+  // check the levels before dealing any
+  if let Some(message) = levels.iter().find_map(|&level| vflevel::check(level)) {
+    return Err(Error::new(&message));
+  }
+  let strategy = vfstrategy::by_name(strategy, budget)?;
+  Ok(Simulation { levels, rounds, seed, strategy })
+}
+
+// reads the options, then the puzzle from the headers, a file or the input
+fn parse(args: &[String], input: &mut dyn Read) -> Result<Options>
+{
+  let mut size = (SIZE, SIZE, MAX);
+  let mut headers: Option<&str> = None;
+  let mut board: Option<&str> = None;
+  let mut file: Option<&str> = None;
+  let mut level: Option<Option<u8>> = None;
+  let mut budget = vfsearch::DEFAULT_BUDGET;
+  let mut strategy = vfstrategy::NAMES[0];
+  let mut json = false;
+
+  // take apart the arguments
+  let mut arg_iter = args.iter().map(|arg| arg.as_str());
+  let mut next = |name: &str| arg_iter.next().ok_or(Error::new(format!("{} needs a value", name).as_str()));
+  loop
+  {
+    let arg = match next("") {
+      Ok(arg) => arg,
+      Err(_) => { break; }
+    };
+    match arg
+    {
+      "--size" =>
+      {
+        let rows = next(arg)?.parse::<usize>()?;
+        let columns = next(arg)?.parse::<usize>()?;
+        let max = next(arg)?.parse::<u8>()?;
+        size = (rows, columns, max);
+      }
+      "--headers" => { headers = Some(next(arg)?); }
+      "--board" => { board = Some(next(arg)?); }
+      "--json" => { json = true; }
+      "--strategy" => { strategy = next(arg)?; }
+      "--level" =>
+      {
+        level = Some(match next(arg)? {
+          "off" => None,
+          value => Some(value.parse::<u8>()?)
+        });
+      }
+      "--budget" =>
+      {
+        let depth = next(arg)?.parse::<usize>()?;
+        let time = next(arg)?.parse::<u64>()?;
+        budget = vfsearch::Budget { depth, time: Duration::from_millis(time) };
+      }
+      _ if arg.starts_with("--") => { return Err(Error::new(format!("Unknown option {}", arg).as_str())); }
+      _ => { file = Some(arg); }
+    }
+  }
+
+  // build the puzzle from the options, or read it
+  let (puzzle, file_level) = match headers
+  {
+    Some(headers) =>
+    {
+      if file.is_some() {
+        return Err(Error::new("Give either headers or a file"));
+      }
+      if let Some(message) = check(size.0, size.1, size.2) {
+        return Err(Error::new(&message));
+      }
+      (build(Puzzle::new(size.0, size.1, size.2), headers, board)?, None)
+    }
+    None =>
+    {
+      if board.is_some() {
+        return Err(Error::new("A board needs headers"));
+      }
+      let mut text = String::new();
+      match file {
+        Some(path) if path != "-" => { text = std::fs::read_to_string(path)?; }
+        _ => { input.read_to_string(&mut text)?; }
+      }
+      vffile::read(&text)?
+    }
+  };
+
+  // an option overrides the level of the file
+  let level = level.unwrap_or(file_level);
+  if let Some(message) = level.and_then(vflevel::check) {
+    return Err(Error::new(&message));
+  }
+
+  let strategy = vfstrategy::by_name(strategy, budget)?;
+  Ok(Options { puzzle, level, strategy, json })
+}
+
+// fills the headers and the board of an empty puzzle from their arguments
+fn build(mut puzzle: Puzzle, headers: &str, board: Option<&str>) -> Result<Puzzle>
+{
+  // the headers, rows then columns
+  let numbers = headers
+    .split_whitespace()
+    .map(|word| word.parse::<u8>())
+    .collect::<std::result::Result<Vec<u8>, _>>()?;
+  if numbers.len() != 2 * (puzzle.rows() + puzzle.columns()) {
+    return Err(Error::new("Headers need points and voltorbs for every row and column"));
+  }
+  let mut labels = numbers.chunks(2).map(|pair| (pair[0], pair[1]));
+  for label in puzzle.right.iter_mut().chain(puzzle.bottom.iter_mut()) {
+    *label = labels.next().ok_or(Error::new("Bad headers"))?;
+  }
+
+  // the revealed cells
+  if let Some(board) = board
+  {
+    let rows: Vec<&str> = board.split_whitespace().collect();
+    if rows.len() != puzzle.rows() {
+      return Err(Error::new("The board needs a word for every row"));
+    }
+    for (row, word) in rows.iter().enumerate()
+    {
+      let cells: Vec<char> = word.chars().collect();
+      if cells.len() != puzzle.columns() {
+        return Err(Error::new("The board needs a char for every cell"));
+      }
+      for (column, &c) in cells.iter().enumerate() {
+        puzzle.board[row][column] = match c {
+          '-' => None,
+          _ => match c.to_digit(10) {
+            Some(value) if puzzle.values().contains(&(value as u8)) => Some(value as u8),
+            _ => { return Err(Error::new("Bad cell on the board")); }
+          }
+        };
+      }
+    }
+  }
+
+  Ok(puzzle)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  // solves with the arguments, reading the rest from the text
+  fn solve_text(args: &[&str], text: &str) -> (i32, String) {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    solve(&args, &mut text.as_bytes())
+  }
+
+  #[test]
+  fn solves_a_file_from_the_input()
+  {
+    // a 2x2 board with one voltorb, where the cell beside it is a certain two
+    let text = "size 2 2 2\nlevel off\nrow 2 1 - -\nrow 2 0 - -\ncolumn 1 1\ncolumn 3 0\n";
+    let (code, output) = solve_text(&["--strategy", "greedy"], text);
+    assert_eq!(code, EXIT_SOLVED);
+    assert!(output.contains("1 solutions"));
+    assert!(output.ends_with("flip 2 1\n"));
+
+    let (code, output) = solve_text(&["--json", "-"], text);
+    assert_eq!(code, EXIT_SOLVED);
+    assert!(output.contains("\"solutions\":1") && output.contains("\"recommendation\":{\"column\":2,\"row\":1}"));
+  }
+
+  #[test]
+  fn reports_unsolvable_and_bad_usage()
+  {
+    let (code, output) = solve_text(&["--size", "2", "2", "2", "--headers", "4 0 4 0 1 0 1 0"], "");
+    assert_eq!(code, EXIT_UNSOLVABLE);
+    assert!(output.contains("0 solutions"));
+    assert_eq!(solve_text(&["--bogus"], "").0, EXIT_USAGE);
+    assert_eq!(solve_text(&[], "not a puzzle").0, EXIT_USAGE);
+  }
+}
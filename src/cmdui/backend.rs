@@ -0,0 +1,128 @@
+// Where the display writes its frames and reads its keys. The terminal uses stdout and stdin;
+// a script types keys from text instead, for running the display without a terminal:
+//
+//   each line is typed, then entered, unless it ends with '\'
+//   <up> <down> <left> <right> <bs> <enter> <esc> <ctrl-x> <lt> stand for keys that aren't chars
+//   a line starting with '#' is a comment
+
+use std::collections::VecDeque;
+use std::io::Write;
+use crate::error::*;
+use crate::keyin::{is_writable, Keyin};
+use super::Key;
+
+// where the render thread writes the screen
+pub trait Sink: Send {
+  fn write(&mut self, text: &str) -> Result<()>;
+}
+
+// where the display reads keys, none at the end of the input
+pub trait Source {
+  fn next_key(&mut self) -> Option<Key>;
+}
+
+// the terminal's screen
+pub struct Stdout;
+impl Sink for Stdout
+{
+  // write in a single flush
+  fn write(&mut self, text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+  }
+}
+
+// nowhere, for a display that is only read through its snapshot
+pub struct Discard;
+impl Sink for Discard
+{
+  // drop the text
+  fn write(&mut self, _text: &str) -> Result<()> {
+    Ok(())
+  }
+}
+
+// the terminal's keys
+impl Source for Keyin
+{
+  // read the next key from stdin
+  fn next_key(&mut self) -> Option<Key> {
+    self.next()
+  }
+}
+
+// keys typed from a script
+pub struct Script {
+  keys: VecDeque<Key>
+}
+impl Script
+{
+  // parse a script, failing on a key it doesn't know
+  pub fn parse(text: &str) -> Result<Self>
+  {
+    let mut keys = VecDeque::new();
+    for (index, line) in text.lines().enumerate()
+    {
+      if line.starts_with('#') {
+        continue;
+      }
+      let fail = |message: &str| Error::new(format!("Line {}: {}", index+1, message).as_str());
+
+      // a trailing backslash keeps typing on the next line
+      let (line, enter) = match line.strip_suffix('\\') {
+        Some(line) => (line, false),
+        None => (line, true)
+      };
+
+      // type the chars, and the named keys between angle brackets
+      let mut rest = line;
+      while let Some(c) = rest.chars().next()
+      {
+        if c != '<' {
+          keys.push_back(if is_writable(c) { Key::Writable(c) } else { Key::Other });
+          rest = &rest[c.len_utf8()..];
+          continue;
+        }
+        let end = rest.find('>').ok_or_else(|| fail("Unclosed key name"))?;
+        keys.push_back(key_named(&rest[1..end]).ok_or_else(|| fail(&format!("Unknown key <{}>", &rest[1..end])))?);
+        rest = &rest[end+1..];
+      }
+      if enter {
+        keys.push_back(Key::Enter);
+      }
+    }
+    Ok(Script { keys })
+  }
+}
+impl Source for Script
+{
+  // type the next key, none after the last
+  fn next_key(&mut self) -> Option<Key> {
+    self.keys.pop_front()
+  }
+}
+
+// the key a name between angle brackets stands for
+fn key_named(name: &str) -> Option<Key>
+{
+  Some(match name {
+    "up" => Key::Up,
+    "down" => Key::Down,
+    "left" => Key::Left,
+    "right" => Key::Right,
+    "bs" => Key::Backspace,
+    "enter" => Key::Enter,
+    "esc" => Key::Escape,
+    "lt" => Key::Writable('<'),
+    _ =>
+    {
+      let letter = name.strip_prefix("ctrl-")?;
+      match letter.as_bytes() {
+        [c] if c.is_ascii_lowercase() => Key::Ctrl(*c as char),
+        _ => { return None; }
+      }
+    }
+  })
+}
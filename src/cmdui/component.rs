@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+use crate::error::*;
+use super::{Key, Rect, UIHandle};
+
+// a panel of the screen that draws itself into a region and may act on keys
+pub trait Component
+{
+  // the (rows, columns) the component needs
+  fn size(&self) -> (usize, usize);
+
+  // draws the component in the region, which is at least its size
+  fn render(&self, display: &UIHandle, region: Rect) -> Result<()>;
+
+  // acts on a key, returning true if the component used it
+  fn handle_key(&mut self, _key: Key) -> bool {
+    false
+  }
+}
+
+// a question above the line where the answer is typed
+pub struct PromptComponent {
+  question: String,
+  width: usize
+}
+impl PromptComponent
+{
+  // make an empty prompt as wide as its widest question
+  pub fn new(width: usize) -> Self {
+    PromptComponent {
+      question: String::new(),
+      width
+    }
+  }
+
+  // sets the question shown above the answer
+  pub fn ask(&mut self, question: &str) {
+    self.question = question.to_owned();
+  }
+
+  // where the answer is typed within the region
+  pub fn input(&self, region: Rect) -> (usize, usize) {
+    (region.row + 1, region.column)
+  }
+}
+impl Component for PromptComponent
+{
+  // the question and the answer
+  fn size(&self) -> (usize, usize) {
+    (2, self.width)
+  }
+
+  // draws the question, leaving the answer to text_command
+  fn render(&self, display: &UIHandle, region: Rect) -> Result<()> {
+    display.render(region.loc(), self.question.chars().take(region.width).collect::<String>())
+  }
+}
+
+// the latest messages, oldest first, wrapped to the width of the region
+pub struct MessageLog {
+  messages: VecDeque<String>,
+  lines: usize
+}
+impl MessageLog
+{
+  // make an empty log that shows a number of lines
+  pub fn new(lines: usize) -> Self {
+    MessageLog {
+      messages: VecDeque::new(),
+      lines
+    }
+  }
+
+  // adds a message, forgetting those that can no longer be shown
+  pub fn push(&mut self, message: &str)
+  {
+    self.messages.push_back(message.to_owned());
+    while self.messages.len() > self.lines {
+      self.messages.pop_front();
+    }
+  }
+}
+impl Component for MessageLog
+{
+  // a row for each line
+  fn size(&self) -> (usize, usize) {
+    (self.lines, 1)
+  }
+
+  // draws the newest lines that fit, wrapping long messages
+  fn render(&self, display: &UIHandle, region: Rect) -> Result<()>
+  {
+    let width = region.width.max(1);
+    let mut lines = Vec::new();
+    for message in &self.messages {
+      let chars: Vec<char> = message.chars().collect();
+      lines.extend(chars.chunks(width).map(|chunk| chunk.iter().collect::<String>()));
+    }
+    let skip = lines.len().saturating_sub(region.height.min(self.lines));
+    for (index, line) in lines.iter().skip(skip).enumerate() {
+      display.render((region.row + index, region.column), line.clone())?;
+    }
+    Ok(())
+  }
+}
@@ -1,71 +1,94 @@
-
-use std::io::{Write};
 use crate::error::*;
+use super::backend::{Sink, Stdout};
+use super::style::{Cell, Style};
 
 const ESC: char = 27 as char;
 
+// shows the cursor and leaves the alternate screen, as one write
+pub const RESET_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+
 // return if a char is writable
 pub fn is_writable(c: char) -> bool {
-  c as u8 > 31 && (c as u8) < 127
+  (c as u32) > 31 && (c as u32) < 127
 }
 
-// print something to the screen
-fn print(c: char) -> Result<()> {
-  print!("{}",c);
-  std::io::stdout().flush()?;
-  Ok(())
+// clear the screen
+pub fn clear_screen(sink: &mut dyn Sink) -> Result<()> {
+  sink.write(format!("{esc}[1;1H{esc}[3J{esc}[0J",esc=ESC).as_str())
 }
 
-// clear the screen
-pub fn clear_screen() -> Result<()> {
-  for c in format!("{esc}[1;1H{esc}[3J{esc}[0J",esc=ESC).chars() {
-    print(c)?;
-  };
-  Ok(())
+// switch the terminal to the alternate screen, keeping the shell's screen to return to
+pub fn enter_alternate_screen() -> Result<()> {
+  Stdout.write(format!("{esc}[?1049h",esc=ESC).as_str())
 }
 
-// hide cursor
-pub fn hide_cursor() -> Result<()> {
-  for c in format!("{esc}[?25l",esc=ESC).chars() {
-    print(c)?;
-  };
-  Ok(())
+// show the terminal's cursor and go back to the shell's screen
+pub fn reset_screen() -> Result<()> {
+  Stdout.write(RESET_SCREEN)
 }
 
-// show cursor
-pub fn show_cursor() -> Result<()> {
-  for c in format!("{esc}[?25h",esc=ESC).chars() {
-    print(c)?;
-  };
-  Ok(())
+// the escape that moves the cursor to a cell of the buffer, which the terminal counts from one
+fn move_to(row: usize, column: usize) -> String {
+  format!("{esc}[{row};{column}H",esc=ESC,row=row+1,column=column+1)
 }
 
-// set the cursor location
-pub fn set_cursor(row: usize, column: usize) -> Result<()> {
-  for c in format!("{esc}[{row};{column}H",esc=ESC,row=row,column=column).chars() {
-    print(c)?;
-  };
-  Ok(())
+// what the screen shows: the chars, and where the cursor is if it's shown
+#[derive(Clone, PartialEq)]
+pub struct Frame {
+  pub buffer: Vec<Vec<Cell>>,
+  pub cursor: Option<(usize, usize)>
 }
 
-// render function
-pub fn render(buffer: &Vec<Vec<char>>) -> Result<()> {
+// render function, drawing only the runs of chars that differ from the last frame
+pub fn render(frame: &Frame, last: Option<&Frame>, sink: &mut dyn Sink) -> Result<()> {
 
-  // make sure cursor at zero and is hidden
-  hide_cursor()?;
-  
   // make sure the characters are whitespace
-  if !buffer.iter().all(|row| row.iter().all(|cell| is_writable(*cell))) {
+  let buffer = &frame.buffer;
+  if !buffer.iter().all(|row| row.iter().all(|cell| is_writable(cell.c))) {
     return Err(Error::new("Invalid char in screen buffer."));
   }
 
-  // print the chars to the screen
+  // hide the cursor while drawing
+  let mut print_string = format!("{esc}[?25l",esc=ESC);
+
+  // add every changed run, moving the cursor once per run and switching styles only when they change
+  let mut style: Option<Style> = None;
   for row in 0..buffer.len() {
-    for column in 0..buffer[row].len() {
-      set_cursor(row,column)?;
-      print(buffer[row][column])?;
+    let last_row = last.and_then(|last| last.buffer.get(row));
+    let mut column = 0;
+    while column < buffer[row].len()
+    {
+      // skip the chars that are already on the screen
+      let same = |column: usize| last_row.and_then(|last_row| last_row.get(column)) == Some(&buffer[row][column]);
+      if same(column) {
+        column += 1;
+        continue;
+      }
+
+      // draw until the chars agree again
+      print_string.push_str(move_to(row, column).as_str());
+      while column < buffer[row].len() && !same(column) {
+        let cell = buffer[row][column];
+        if style != Some(cell.style) {
+          print_string.push_str(cell.style.escape().as_str());
+          style = Some(cell.style);
+        }
+        print_string.push(cell.c);
+        column += 1;
+      }
     }
   }
 
-  Ok(())
+  // leave the terminal plain for anything else that writes
+  if style.is_some() {
+    print_string.push_str(Style::PLAIN.escape().as_str());
+  }
+
+  // set the location of the cursor, or leave it hidden
+  if let Some((row, column)) = frame.cursor {
+    print_string.push_str(move_to(row, column).as_str());
+    print_string.push_str(format!("{esc}[?25h",esc=ESC).as_str());
+  }
+
+  sink.write(print_string.as_str())
 }
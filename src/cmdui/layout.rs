@@ -0,0 +1,56 @@
+// a rectangle of the screen that panels are cut from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rect {
+  pub row: usize,
+  pub column: usize,
+  pub height: usize,
+  pub width: usize
+}
+impl Rect
+{
+  // make a rectangle from its top left corner and size
+  pub fn new(row: usize, column: usize, height: usize, width: usize) -> Self {
+    Rect {
+      row,
+      column,
+      height,
+      width
+    }
+  }
+
+  // the top left corner, where text is rendered
+  pub fn loc(&self) -> (usize, usize) {
+    (self.row, self.column)
+  }
+
+  // cuts off the top rows, returning them and the rest, none if they don't fit
+  pub fn take_top(&self, height: usize) -> Option<(Rect, Rect)>
+  {
+    if height > self.height {
+      return None;
+    }
+    Some((
+      Rect::new(self.row, self.column, height, self.width),
+      Rect::new(self.row + height, self.column, self.height - height, self.width)
+    ))
+  }
+
+  // cuts off the bottom rows, returning them and the rest, none if they don't fit
+  pub fn take_bottom(&self, height: usize) -> Option<(Rect, Rect)>
+  {
+    let (rest, bottom) = self.take_top(self.height.checked_sub(height)?)?;
+    Some((bottom, rest))
+  }
+
+  // cuts off the left columns, returning them and the rest, none if they don't fit
+  pub fn take_left(&self, width: usize) -> Option<(Rect, Rect)>
+  {
+    if width > self.width {
+      return None;
+    }
+    Some((
+      Rect::new(self.row, self.column, self.height, width),
+      Rect::new(self.row, self.column + width, self.height, self.width - width)
+    ))
+  }
+}
@@ -1,5 +1,12 @@
+mod backend;
+mod component;
 mod io;
+mod layout;
+mod restore;
+mod style;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::{
   Arc,
   Mutex,
@@ -8,88 +15,212 @@ use std::sync::{
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
 use termios::*;
-use termion::event::Key;
-use termion::input::TermRead;
+pub use crate::keyin::Key;
+pub use backend::{Discard, Script, Sink, Source, Stdout};
+pub use component::{Component, MessageLog, PromptComponent};
+pub use layout::Rect;
+pub use style::{Color, Span, Style, Text};
+use style::Cell;
 use crate::error::*;
 use crate::util::*;
 
 // constants
 const STDIN_FD: std::os::unix::io::RawFd = 0;
 const SLEEP_TIME: Duration  = Duration::from_millis(100);
-const EMPTY: char = ' ';
-
-// static variables
-static STARTED: AtomicBool = AtomicBool::new(false);
+const EMPTY: Cell = Cell { c: ' ', style: Style::PLAIN };
+const RENDER_THREAD: &str = "cmdui";
+const STOP_WAIT: Duration = Duration::from_millis(200);
+const DEFAULT_SIZE: (usize, usize) = (24, 80);
+
+// the flags of the display on the terminal, so a panic can stop it drawing
+static TERMINAL: Mutex<Option<Arc<Flags>>> = Mutex::new(None);
+
+// whether a render thread should keep drawing, and whether it's in the middle of a frame
+#[derive(Default)]
+struct Flags {
+  running: AtomicBool,
+  drawing: AtomicBool
+}
 
-// Starts the thread and returns its UIHandle
-pub fn init(width: usize, height: usize) -> Result<UIHandle>
+// Starts the thread and returns its UIHandle, sized to the terminal
+pub fn init() -> Result<UIHandle>
 {
-  // if the thread is started, return an error
-  if STARTED.load(Relaxed) == true {
+  // if the terminal already has a display, return an error
+  if lock(&TERMINAL).as_ref().is_some_and(|flags| flags.running.load(Relaxed)) {
     return Err(Error::new("Display already started."));
   }
 
-  // disable input with termios
-  let mut termios = Termios::from_fd(STDIN_FD)?;
+  // disable input with termios, saving the settings to restore
+  let saved = Termios::from_fd(STDIN_FD)?;
+  restore::install(saved);
+  let mut termios = saved;
   termios.c_lflag &= !ECHO;
   termios.c_lflag &= !ICANON;
+
+  // let ctrl-z reach the input instead of suspending, so it can be bound
+  termios.c_cc[VSUSP] = 0;
   tcsetattr(STDIN_FD, TCSANOW, &termios)?;
 
-  // create the display
+  // create the display, and let a resize interrupt the wait for a key
+  let (height, width) = terminal_size().unwrap_or(DEFAULT_SIZE);
   let model = Arc::new(Mutex::new(UIModel::new(width, height)));
+  install_resize()?;
 
-  // create graphical loop thread
-  let thread_model = model.clone();
-  let thread = thread::spawn(|| {
-    // clear the screen initially
-    io::clear_screen().unwrap();
-    STARTED.store(true, Relaxed);
-    mainloop(thread_model).unwrap();
-    STARTED.store(false, Relaxed);
-  });
+  // draw on the alternate screen, leaving the shell's alone
+  io::enter_alternate_screen()?;
 
   // return the devices
-  return Ok(UIHandle::new(model, thread));
+  let (thread, flags) = start(model.clone(), Box::new(Stdout))?;
+  *lock(&TERMINAL) = Some(flags.clone());
+  Ok(UIHandle::new(model, thread, flags, Some(saved), Box::new(crate::keyin::Keyin::new())))
+}
+
+// Starts the thread without touching the terminal, reading keys from the source and writing frames to the sink;
+// any number may run at once
+pub fn headless(size: (usize, usize), source: Box<dyn Source>, sink: Box<dyn Sink>) -> Result<UIHandle>
+{
+  // create the display at the size given
+  let model = Arc::new(Mutex::new(UIModel::new(size.1, size.0)));
+  let (thread, flags) = start(model.clone(), sink)?;
+  Ok(UIHandle::new(model, thread, flags, None, source))
+}
+
+// creates the graphical loop thread, drawing the model to the sink, with the flags that stop it
+fn start(model: Arc<Mutex<UIModel>>, sink: Box<dyn Sink>) -> Result<(JoinHandle<()>, Arc<Flags>)>
+{
+  let flags = Arc::new(Flags::default());
+  flags.running.store(true, Relaxed);
+  let thread_flags = flags.clone();
+  let thread = thread::Builder::new().name(RENDER_THREAD.to_owned()).spawn(move || {
+    block_resize();
+    mainloop(model, sink, &thread_flags).ok();
+    thread_flags.drawing.store(false, Relaxed);
+    thread_flags.running.store(false, Relaxed);
+  })?;
+  Ok((thread, flags))
+}
+
+// the size of the terminal as (rows, columns), if it can be found
+fn terminal_size() -> Option<(usize, usize)>
+{
+  let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+  let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+  if result == 0 && size.ws_row > 0 && size.ws_col > 0 {
+    Some((size.ws_row as usize, size.ws_col as usize))
+  }
+  else {
+    None
+  }
+}
+
+// does nothing, so that a resize only interrupts the read of a key
+extern "C" fn on_resize(_signal: libc::c_int) {}
+
+// catches resizes without restarting reads, so waiting for a key can notice them
+fn install_resize() -> Result<()>
+{
+  unsafe {
+    let mut action: libc::sigaction = std::mem::zeroed();
+    action.sa_sigaction = on_resize as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    action.sa_flags = 0;
+    libc::sigemptyset(&mut action.sa_mask);
+    if libc::sigaction(libc::SIGWINCH, &action, std::ptr::null_mut()) != 0 {
+      return Err(std::io::Error::last_os_error().into());
+    }
+  }
+  Ok(())
+}
+
+// keeps resizes away from the render thread, so they reach the thread reading keys
+fn block_resize()
+{
+  unsafe {
+    let mut set: libc::sigset_t = std::mem::zeroed();
+    libc::sigemptyset(&mut set);
+    libc::sigaddset(&mut set, libc::SIGWINCH);
+    libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut());
+  }
+}
+
+// asks the render thread to stop, waiting briefly for it to finish the frame it's drawing
+fn stop_rendering(flags: &Flags)
+{
+  flags.running.store(false, Relaxed);
+  if thread::current().name() == Some(RENDER_THREAD) {
+    return;
+  }
+  let deadline = std::time::Instant::now() + STOP_WAIT;
+  while flags.drawing.load(Relaxed) && std::time::Instant::now() < deadline {
+    thread::sleep(Duration::from_millis(1));
+  }
+}
+
+// stops the display on the terminal if there is one, skipping it if the panic came while its flags were taken
+fn stop_terminal()
+{
+  if let Ok(terminal) = TERMINAL.try_lock() {
+    if let Some(flags) = terminal.as_ref() {
+      stop_rendering(flags);
+    }
+  }
 }
 
 // renders the bits to the page
-fn mainloop(model: Arc<Mutex<UIModel>>) -> Result<()>
+fn mainloop(model: Arc<Mutex<UIModel>>, mut sink: Box<dyn Sink>, flags: &Flags) -> Result<()>
 {
   // clear the screen initially
-  io::clear_screen()?;
+  io::clear_screen(sink.as_mut())?;
 
-  // start loop
-  loop
+  // start loop, remembering what is on the screen
+  let mut last: Option<io::Frame> = None;
+  while flags.running.load(Relaxed)
   {
-    // display
-    io::render(&lock(&model).buffer)?;
-
-    // set the location of the cursor, or remove the cursor
-    match lock(&model).get_cursor() {
-      None => { io::hide_cursor()?; }
-      Some((row,column)) => {
-        io::set_cursor(row, column)?;
-        io::show_cursor()?;
+    // take the frame only if the model changed since the last one
+    let frame = lock(&model).take_frame();
+
+    // display the differences, or everything after a resize
+    if let Some((frame, repaint)) = frame {
+      if repaint {
+        io::clear_screen(sink.as_mut())?;
+        last = None;
       }
-    };
-    
+      flags.drawing.store(true, Relaxed);
+      io::render(&frame, last.as_ref(), sink.as_mut())?;
+      flags.drawing.store(false, Relaxed);
+      last = Some(frame);
+    }
+
     // sleep the thread
     thread::sleep(SLEEP_TIME);
   }
+
+  Ok(())
 }
 
 
 pub struct UIHandle {
   model: Arc<Mutex<UIModel>>,
-  thread: JoinHandle<()>
+  thread: Option<JoinHandle<()>>,
+  flags: Arc<Flags>,
+  saved: Option<Termios>,
+  source: RefCell<Box<dyn Source>>
 }
 impl UIHandle
 {
-  // make a new uihandle
-  fn new(model: Arc<Mutex<UIModel>>, thread: JoinHandle<()>) -> Self {
+  // make a new uihandle, with the terminal settings to restore unless it's headless
+  fn new(
+    model: Arc<Mutex<UIModel>>,
+    thread: JoinHandle<()>,
+    flags: Arc<Flags>,
+    saved: Option<Termios>,
+    source: Box<dyn Source>
+  ) -> Self {
     UIHandle {
       model,
-      thread
+      thread: Some(thread),
+      flags,
+      saved,
+      source: RefCell::new(source)
     }
   }
 
@@ -105,8 +236,8 @@ impl UIHandle
   }
 
   // render function
-  pub fn render(&self, loc: (usize, usize), string: String) -> Result<()> {
-    lock(&self.model).render(loc, string)?;
+  pub fn render<T: Into<Text>>(&self, loc: (usize, usize), text: T) -> Result<()> {
+    lock(&self.model).render(loc, text.into())?;
     Ok(())
   }
 
@@ -115,9 +246,57 @@ impl UIHandle
     lock(&self.model).clear();
   }
 
-  // join
-  pub fn join(self) -> Result<()> {
-    self.thread.join()?;
+  // makes every change in the closure reach the screen together, as one frame
+  pub fn batch<T, F: FnOnce() -> Result<T>>(&self, changes: F) -> Result<T>
+  {
+    lock(&self.model).held = true;
+    let result = changes();
+    lock(&self.model).held = false;
+    result
+  }
+
+  // the size of the display as (rows, columns)
+  pub fn size(&self) -> (usize, usize) {
+    let model = lock(&self.model);
+    (model.height, model.width)
+  }
+
+  // the chars on the display, a line per row without the trailing blanks
+  pub fn snapshot(&self) -> Vec<String> {
+    lock(&self.model).snapshot()
+  }
+
+  // the next key from the source, none at the end of the input
+  pub fn next_key(&self) -> Option<Key> {
+    self.source.borrow_mut().next_key()
+  }
+
+  // fits the display to the terminal again, keeping the size of a headless one
+  pub fn resize(&self) -> Result<()> {
+    if self.saved.is_none() {
+      return Ok(());
+    }
+    let (height, width) = terminal_size().ok_or(Error::new("Terminal size unknown."))?;
+    lock(&self.model).resize(width, height);
+    Ok(())
+  }
+
+  // make a key enter a command by itself
+  pub fn bind(&self, key: Key, command: &str) {
+    lock(&self.model).bindings.insert(key, command.to_owned());
+  }
+
+  // stops the render thread and puts the terminal back as it was
+  pub fn shutdown(&mut self) -> Result<()>
+  {
+    stop_rendering(&self.flags);
+    if let Some(thread) = self.thread.take() {
+      thread.join()?;
+    }
+    if let Some(saved) = &self.saved {
+      io::reset_screen()?;
+      tcsetattr(STDIN_FD, TCSANOW, saved)?;
+    }
     Ok(())
   }
 
@@ -131,15 +310,34 @@ impl UIHandle
     self.set_cursor(loc.0, loc.1)?;
     let mut cursor = self.get_cursor().ok_or(Error::new("Cursor not set."))?;
 
-    // iterate over chars
-    for key in std::io::stdin().keys() {
-      match key? {
-        Key::Char('\n') =>
+    // iterate over keys until the command is entered
+    loop {
+      let key = self.next_key().ok_or(Error::new("No more input"))?;
+
+      // a resize ends an empty command, so the caller can lay the screen out again
+      if key == Key::Resize {
+        self.resize()?;
+        if command.is_empty() {
+          break;
+        }
+        continue;
+      }
+
+      // a bound key enters its command if nothing has been typed
+      if command.is_empty() {
+        if let Some(bound) = lock(&self.model).bindings.get(&key) {
+          command = bound.clone();
+          break;
+        }
+      }
+
+      match key {
+        Key::Enter =>
         {
           self.set_cursor(loc.0, loc.1)?;
           break;
         }
-        Key::Char(c) => {
+        Key::Writable(c) => {
           cursor.1 += 1;
           self.set_cursor(cursor.0, cursor.1)?;
           command.push(c);
@@ -172,11 +370,24 @@ impl UIHandle
 
 
 
+impl Drop for UIHandle
+{
+  // restore the terminal however the handle goes away
+  fn drop(&mut self) {
+    self.shutdown().ok();
+  }
+}
+
+
 struct UIModel {
   width: usize,
   height: usize,
-  buffer: Vec<Vec<char>>,
-  cursor: Option<(usize, usize)>
+  buffer: Vec<Vec<Cell>>,
+  cursor: Option<(usize, usize)>,
+  bindings: HashMap<Key, String>,
+  dirty: bool,
+  held: bool,
+  repaint: bool
 }
 impl UIModel
 {
@@ -186,10 +397,44 @@ impl UIModel
       width,
       height,
       buffer: vec![vec![EMPTY;width];height],
-      cursor: None
+      cursor: None,
+      bindings: HashMap::new(),
+      dirty: true,
+      held: false,
+      repaint: false
     }
   }
 
+  // returns what to draw if anything has changed since it was last taken
+  fn take_frame(&mut self) -> Option<(io::Frame, bool)>
+  {
+    if !self.dirty || self.held {
+      return None;
+    }
+    self.dirty = false;
+    let frame = io::Frame {
+      buffer: self.buffer.clone(),
+      cursor: self.cursor
+    };
+    Some((frame, std::mem::take(&mut self.repaint)))
+  }
+
+  // changes the size, keeping what still fits and drawing everything again
+  fn resize(&mut self, width: usize, height: usize)
+  {
+    let mut buffer = vec![vec![EMPTY;width];height];
+    for row in 0..height.min(self.height) {
+      for column in 0..width.min(self.width) {
+        buffer[row][column] = self.buffer[row][column];
+      }
+    }
+    self.buffer = buffer;
+    self.width = width;
+    self.height = height;
+    self.dirty = true;
+    self.repaint = true;
+  }
+
   // returns the position of the cursor
   fn get_cursor(&self) -> Option<(usize, usize)> {
     self.cursor
@@ -198,21 +443,24 @@ impl UIModel
   // sets the cursor position
   fn set_cursor(&mut self, height: usize, width: usize) -> Result<()> {
     self.cursor = Some((height, width));
+    self.dirty = true;
     Ok(())
   }
 
   // render a component
-  fn render(&mut self, loc: (usize, usize), string: String) -> Result<()> {
+  fn render(&mut self, loc: (usize, usize), text: Text) -> Result<()> {
 
     // init the local cursor
     let mut cursor: (usize,usize) = loc;
 
-    // iterate over the chars
-    for c in string.chars()
+    // iterate over the chars with their styles
+    for (c, style) in text.0.iter().flat_map(|span| span.text.chars().map(move |c| (c, span.style)))
     {
       // if c is a whitespace char and we're in bounds, render
       if io::is_writable(c) && cursor.0 < self.height && cursor.1 < self.width {
-        self.buffer[cursor.0][cursor.1] = c;
+        let cell = Cell { c, style };
+        self.dirty |= self.buffer[cursor.0][cursor.1] != cell;
+        self.buffer[cursor.0][cursor.1] = cell;
         
         // increment the cursor
         cursor = (cursor.0, cursor.1+1);
@@ -227,8 +475,14 @@ impl UIModel
     Ok(())
   }
 
+  // the chars of each row, without the trailing blanks
+  fn snapshot(&self) -> Vec<String> {
+    self.buffer.iter().map(|row| row.iter().map(|cell| cell.c).collect::<String>().trim_end().to_owned()).collect()
+  }
+
   // clear the display
   fn clear(&mut self) {
     self.buffer = vec![vec![EMPTY;self.width];self.height];
+    self.dirty = true;
   }
-}
\ No newline at end of file
+}
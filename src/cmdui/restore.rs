@@ -0,0 +1,48 @@
+use std::sync::OnceLock;
+use termios::*;
+use super::{io, STDIN_FD};
+
+// the settings the terminal had before the display started
+static SAVED: OnceLock<Termios> = OnceLock::new();
+
+// remembers the settings, and restores them on a panic or a signal
+pub fn install(saved: Termios)
+{
+  SAVED.set(saved).ok();
+
+  // restore before the panic message, so it prints on the normal screen
+  let previous = std::panic::take_hook();
+  std::panic::set_hook(Box::new(move |info| {
+    super::stop_terminal();
+    restore();
+    previous(info);
+  }));
+
+  // restore and exit on an interrupt or termination
+  let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+  unsafe {
+    libc::signal(libc::SIGINT, handler);
+    libc::signal(libc::SIGTERM, handler);
+  }
+}
+
+// puts the screen and the terminal settings back, using only calls a signal handler may make
+pub fn restore()
+{
+  let escapes = io::RESET_SCREEN.as_bytes();
+  unsafe {
+    libc::write(libc::STDOUT_FILENO, escapes.as_ptr() as *const libc::c_void, escapes.len());
+  }
+  if let Some(saved) = SAVED.get() {
+    tcsetattr(STDIN_FD, TCSANOW, saved).ok();
+  }
+}
+
+// restores the terminal, then exits as the signal would have
+extern "C" fn on_signal(signal: libc::c_int)
+{
+  restore();
+  unsafe {
+    libc::_exit(128 + signal);
+  }
+}
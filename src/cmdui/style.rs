@@ -0,0 +1,130 @@
+const ESC: char = 27 as char;
+
+// the eight terminal colors, or whatever the terminal draws by default
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[allow(dead_code)]
+pub enum Color {
+  Default,
+  Black,
+  Red,
+  Green,
+  Yellow,
+  Blue,
+  Magenta,
+  Cyan,
+  White
+}
+impl Color
+{
+  // the offset of the color in the select graphic rendition codes
+  fn offset(&self) -> u8 {
+    match self {
+      Color::Black => 0,
+      Color::Red => 1,
+      Color::Green => 2,
+      Color::Yellow => 3,
+      Color::Blue => 4,
+      Color::Magenta => 5,
+      Color::Cyan => 6,
+      Color::White => 7,
+      Color::Default => 9
+    }
+  }
+}
+
+// how a char is drawn
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Style {
+  pub fg: Color,
+  pub bg: Color,
+  pub bold: bool,
+  pub underline: bool
+}
+impl Style
+{
+  // the style of unstyled text
+  pub const PLAIN: Style = Style { fg: Color::Default, bg: Color::Default, bold: false, underline: false };
+
+  // plain text in a color
+  pub const fn fg(fg: Color) -> Self {
+    Style { fg, ..Style::PLAIN }
+  }
+
+  // the same style on a background
+  pub const fn on(self, bg: Color) -> Self {
+    Style { bg, ..self }
+  }
+
+  // the same style in bold
+  pub const fn bold(self) -> Self {
+    Style { bold: true, ..self }
+  }
+
+  // the same style underlined
+  pub const fn underline(self) -> Self {
+    Style { underline: true, ..self }
+  }
+
+  // the escape that switches the terminal to the style
+  pub fn escape(&self) -> String
+  {
+    let mut escape = format!("{esc}[0;{};{}", 30 + self.fg.offset(), 40 + self.bg.offset(), esc=ESC);
+    if self.bold {
+      escape.push_str(";1");
+    }
+    if self.underline {
+      escape.push_str(";4");
+    }
+    escape.push('m');
+    escape
+  }
+}
+
+// a char of the screen with its style
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cell {
+  pub c: char,
+  pub style: Style
+}
+
+// text drawn in a single style
+#[derive(Clone, PartialEq, Debug)]
+pub struct Span {
+  pub text: String,
+  pub style: Style
+}
+impl Span
+{
+  // make a span of text in a style
+  pub fn new(text: impl Into<String>, style: Style) -> Self {
+    Span {
+      text: text.into(),
+      style
+    }
+  }
+}
+
+// text made of spans, which render takes
+#[derive(Clone, PartialEq, Debug)]
+pub struct Text(pub Vec<Span>);
+impl From<String> for Text
+{
+  // plain text
+  fn from(text: String) -> Self {
+    Text(vec![Span::new(text, Style::PLAIN)])
+  }
+}
+impl From<Span> for Text
+{
+  // a single span
+  fn from(span: Span) -> Self {
+    Text(vec![span])
+  }
+}
+impl From<Vec<Span>> for Text
+{
+  // spans in order
+  fn from(spans: Vec<Span>) -> Self {
+    Text(spans)
+  }
+}
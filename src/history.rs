@@ -0,0 +1,115 @@
+// constants
+const MAX_STATES: usize = 100;
+
+// the states before and after the current one, for undo and redo
+pub struct History<T> {
+  past: Vec<T>,
+  future: Vec<T>
+}
+impl<T> History<T>
+{
+  // makes an empty history
+  pub fn new() -> Self {
+    History {
+      past: Vec::new(),
+      future: Vec::new()
+    }
+  }
+
+  // remembers the state before a change, forgetting anything undone
+  pub fn record(&mut self, state: T)
+  {
+    if self.past.len() == MAX_STATES {
+      self.past.remove(0);
+    }
+    self.past.push(state);
+    self.future.clear();
+  }
+
+  // steps the current state back, returning false if there's nothing to undo
+  pub fn undo(&mut self, current: &mut T) -> bool
+  {
+    match self.past.pop() {
+      Some(state) =>
+      {
+        self.future.push(std::mem::replace(current, state));
+        true
+      }
+      None => false
+    }
+  }
+
+  // steps the current state forward, returning false if there's nothing to redo
+  pub fn redo(&mut self, current: &mut T) -> bool
+  {
+    match self.future.pop() {
+      Some(state) =>
+      {
+        self.past.push(std::mem::replace(current, state));
+        true
+      }
+      None => false
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn record_after_undo_drops_the_redo()
+  {
+    let mut history = History::new();
+    let mut current = 0;
+    for next in 1..=3 {
+      history.record(current);
+      current = next;
+    }
+    assert!(history.undo(&mut current));
+    assert_eq!(current, 2);
+    history.record(current);
+    current = 10;
+    assert!(!history.redo(&mut current));
+    assert_eq!(current, 10);
+    assert!(history.undo(&mut current));
+    assert_eq!(current, 2);
+  }
+
+  #[test]
+  fn cap_forgets_the_oldest_state()
+  {
+    let mut history = History::new();
+    let mut current = 0;
+    for next in 1..=MAX_STATES + 5 {
+      history.record(current);
+      current = next;
+    }
+    let mut undone = 0;
+    while history.undo(&mut current) {
+      undone += 1;
+    }
+    assert_eq!(undone, MAX_STATES);
+    assert_eq!(current, 5);
+  }
+
+  #[test]
+  fn undo_and_redo_at_the_ends_do_nothing()
+  {
+    let mut history: History<i32> = History::new();
+    let mut current = 7;
+    assert!(!history.undo(&mut current));
+    assert!(!history.redo(&mut current));
+    assert_eq!(current, 7);
+
+    history.record(current);
+    current = 8;
+    assert!(history.undo(&mut current));
+    assert!(!history.undo(&mut current));
+    assert_eq!(current, 7);
+    assert!(history.redo(&mut current));
+    assert!(!history.redo(&mut current));
+    assert_eq!(current, 8);
+  }
+}
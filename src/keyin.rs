@@ -1,16 +1,77 @@
-use std::io::Stdin;
+use std::io::ErrorKind;
 
-const ESC: char = 27 as char;
+const ESC: u8 = 27;
+const BACKSPACE: u8 = 127;
+const CTRL_H: u8 = 8;
+
+// how long to wait after escape for the rest of an arrow before taking it as the escape key
+const ESCAPE_WAIT_MS: libc::c_int = 50;
 
 // return if a char is writable
 pub fn is_writable(c: char) -> bool {
-  c as u8 > 31 && (c as u8) < 127
+  c as u32 > 31 && (c as u32) < 127
 }
 
+// reads keys from stdin, which must already be out of canonical mode; it reads the descriptor
+// unbuffered, so waiting on it sees every byte not yet taken
+pub struct Keyin {
+  pending: Option<u8>
+}
+impl Keyin
+{
+  // make a new key reader
+  pub fn new() -> Self {
+    Keyin {
+      pending: None
+    }
+  }
 
+  // read a single byte, none at the end of the input
+  fn read(&mut self) -> std::io::Result<Option<u8>>
+  {
+    if let Some(byte) = self.pending.take() {
+      return Ok(Some(byte));
+    }
+    let mut buffer = [0u8; 1];
+    let count = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr() as *mut libc::c_void, 1) };
+    match count {
+      1 => Ok(Some(buffer[0])),
+      0 => Ok(None),
+      _ => Err(std::io::Error::last_os_error())
+    }
+  }
+}
+impl Bytes for Keyin
+{
+  // read a single byte of a key already begun, or none at the end of the input
+  fn byte(&mut self) -> Option<u8> {
+    loop {
+      match self.read() {
+        Err(error) if error.kind() == ErrorKind::Interrupted => {}
+        result => { return result.ok().flatten(); }
+      }
+    }
+  }
 
-pub struct Keyin {
-  stdin: Stdin
+  // returns true if another byte arrives within the wait
+  fn follows(&mut self) -> bool
+  {
+    if self.pending.is_some() {
+      return true;
+    }
+    let mut poll = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    loop {
+      match unsafe { libc::poll(&mut poll, 1, ESCAPE_WAIT_MS) } {
+        -1 if std::io::Error::last_os_error().kind() == ErrorKind::Interrupted => {}
+        result => { return result > 0; }
+      }
+    }
+  }
+
+  // keep the byte for the next read
+  fn unread(&mut self, byte: u8) {
+    self.pending = Some(byte);
+  }
 }
 impl Iterator for Keyin
 {
@@ -20,15 +81,135 @@ impl Iterator for Keyin
   // get the next key
   fn next(&mut self) -> Option<Self::Item>
   {
-
+    // a signal before any key means the terminal changed size
+    let byte = match self.read() {
+      Ok(byte) => byte?,
+      Err(error) if error.kind() == ErrorKind::Interrupted => { return Some(Key::Resize); }
+      Err(_) => { return None; }
+    };
+    decode(byte, self)
   }
 }
 
+// where the bytes of a key come from
+trait Bytes {
+  fn byte(&mut self) -> Option<u8>;
+  fn follows(&mut self) -> bool;
+  fn unread(&mut self, byte: u8);
+}
+
+// the key a byte begins, reading the rest of it
+fn decode(byte: u8, bytes: &mut impl Bytes) -> Option<Key>
+{
+  Some(match byte
+  {
+    // a sequence is sent as escape then more, while the escape key comes alone
+    ESC if !bytes.follows() => Key::Escape,
+    ESC => match bytes.byte()? {
+      b'[' => control_sequence(bytes)?,
+
+      // a key typed right after escape is kept for the next read
+      other => {
+        bytes.unread(other);
+        Key::Escape
+      }
+    },
+    BACKSPACE | CTRL_H => Key::Backspace,
+    b'\n' | b'\r' => Key::Enter,
+    1..=26 => Key::Ctrl((b'a' + byte - 1) as char),
+    _ if is_writable(byte as char) => Key::Writable(byte as char),
+    _ => Key::Other
+  })
+}
+
+// the key of a sequence after escape and bracket, read up to its final byte so none of it is taken as typing;
+// only a bare arrow is known
+fn control_sequence(bytes: &mut impl Bytes) -> Option<Key>
+{
+  let mut parameters = false;
+  loop {
+    match bytes.byte()? {
+      0x20..=0x3f => { parameters = true; }
+      b'A' if !parameters => { return Some(Key::Up); }
+      b'B' if !parameters => { return Some(Key::Down); }
+      b'C' if !parameters => { return Some(Key::Right); }
+      b'D' if !parameters => { return Some(Key::Left); }
+      0x40..=0x7e => { return Some(Key::Other); }
+
+      // a byte that can't be in a sequence ends it, and starts the next key
+      other => {
+        bytes.unread(other);
+        return Some(Key::Other);
+      }
+    }
+  }
+}
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Key {
   Up,
   Down,
   Left,
   Right,
-  Writable(char)
-}
\ No newline at end of file
+  Backspace,
+  Enter,
+  Escape,
+  Ctrl(char),
+  Writable(char),
+  Resize,
+  Other
+}
+
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use std::collections::VecDeque;
+
+  // bytes already typed, all arriving at once
+  struct Typed(VecDeque<u8>);
+  impl Bytes for Typed
+  {
+    fn byte(&mut self) -> Option<u8> {
+      self.0.pop_front()
+    }
+    fn follows(&mut self) -> bool {
+      !self.0.is_empty()
+    }
+    fn unread(&mut self, byte: u8) {
+      self.0.push_front(byte);
+    }
+  }
+
+  // the keys the bytes make
+  fn keys(bytes: &[u8]) -> Vec<Key>
+  {
+    let mut typed = Typed(bytes.iter().copied().collect());
+    let mut keys = Vec::new();
+    while let Some(byte) = typed.byte() {
+      keys.push(decode(byte, &mut typed).unwrap());
+    }
+    keys
+  }
+
+  #[test]
+  fn arrows_and_a_lone_escape() {
+    assert_eq!(keys(b"\x1b[A\x1b[D"), vec![Key::Up, Key::Left]);
+    assert_eq!(keys(b"\x1b"), vec![Key::Escape]);
+    assert_eq!(keys(b"\x1bq"), vec![Key::Escape, Key::Writable('q')]);
+  }
+
+  #[test]
+  fn whole_sequences_are_consumed() {
+    assert_eq!(keys(b"\x1b[1;5Cx"), vec![Key::Other, Key::Writable('x')]);
+    assert_eq!(keys(b"\x1b[15~1"), vec![Key::Other, Key::Writable('1')]);
+    assert_eq!(keys(b"\x1b[Z"), vec![Key::Other]);
+    assert_eq!(keys(b"\x1b[2\r"), vec![Key::Other, Key::Enter]);
+  }
+
+  #[test]
+  fn plain_bytes() {
+    assert_eq!(keys(b"a\x7f\x08\r\x1a"), vec![Key::Writable('a'), Key::Backspace, Key::Backspace, Key::Enter, Key::Ctrl('z')]);
+  }
+}
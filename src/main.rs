@@ -1,105 +1,542 @@
+// the boards are indexed by row and column throughout
+#![allow(clippy::needless_range_loop)]
+
 mod vflip;
-mod vfio;
+mod vflevel;
+mod vfsearch;
+mod vffile;
+mod vfgen;
+mod vfsim;
+mod vfstrategy;
+mod vfjson;
+mod batch;
 mod cmdui;
 mod vflipuic;
 mod error;
 mod util;
-//mod keyin;
+mod history;
+mod keyin;
 
 
 use crate::error::*;
+use crate::cmdui::{Component, Rect};
+use crate::history::History;
 
-const WIDTH: usize = 80;
-const HEIGHT: usize = 20;
-const BOARD_LOC: (usize, usize) = (2,3);
-const STATUS_LOC: (usize, usize) = (2,33);
-const NEXT_LOC: (usize, usize) = (12,3);
-const REC_LOC: (usize, usize) = (12,33);
-const CURSOR_DEFAULT: (usize, usize) = (HEIGHT-1, 1);
-const QUESTION_LOC: (usize,usize) = (HEIGHT-2, 1);
+const MARGIN: (usize, usize) = (2, 3);
+const GAP: usize = 1;
+const LOG_LINES: usize = 3;
+const PROMPT_WIDTH: usize = 60;
+const SCRIPT_SIZE: (usize, usize) = (24, 80);
+const TOO_SMALL: &str = "terminal too small, make it bigger or type quit";
+const NAV_HINT: &str = "arrows move, digit sets, backspace clears, m marks, enter leaves";
+const NAV_MEMO_HINT: &str = "marking: digit toggles, backspace unmarks, m sets, enter leaves";
+const PLAY_HINT: &str = "arrows move, space flips, digit marks, h hints, q quits";
+const SETTLE_LEGEND: &str = "right: the other cells each flip settles";
+const COMMANDS: [&str; 22] = [
+    "help", "quit", "undo", "redo", "nav", "solve", "board", "set", "rec", "reset", "clear",
+    "new", "deal", "play", "headers", "level", "memo", "budget", "strategy", "overlay", "save", "load"
+];
 
-fn main() -> Result<()>
+// how the solver weighs and searches the boards, and chooses the flip
+struct Settings {
+    level: Option<u8>,
+    budget: vfsearch::Budget,
+    strategy_name: String,
+    strategy: Box<dyn vfstrategy::Strategy>,
+    overlay: bool
+}
+
+// where each panel of the screen is drawn
+struct Layout {
+    board: Rect,
+    status: Rect,
+    rec: Rect,
+    next: Rect,
+    log: Rect,
+    prompt: Rect
+}
+
+// the panels of the screen
+struct Screen {
+    board: vflipuic::BoardComponent,
+    status: vflipuic::ProbabilityComponent,
+    rec: vflipuic::RecommendationComponent,
+    next: vflipuic::PreviewComponent,
+    log: cmdui::MessageLog,
+    prompt: cmdui::PromptComponent,
+    hints: bool
+}
+impl Screen
 {
-    // create constants
-    let mut right = [(0,0);5];
-    let mut bottom = [(0,0);5];
+    // makes the panels for a puzzle the solver hasn't seen yet
+    fn new(puzzle: &vflip::Puzzle) -> Self {
+        Screen {
+            board: vflipuic::BoardComponent::new(puzzle.clone()),
+            status: vflipuic::ProbabilityComponent::new(puzzle.rows(), puzzle.columns()),
+            rec: vflipuic::RecommendationComponent::new(puzzle.rows(), puzzle.columns()),
+            next: vflipuic::PreviewComponent::new(puzzle.rows(), puzzle.columns()),
+            log: cmdui::MessageLog::new(LOG_LINES),
+            prompt: cmdui::PromptComponent::new(PROMPT_WIDTH),
+            hints: true
+        }
+    }
 
-    // set the base board
-    let mut board = vflip::init();
+    // shows the solver's panels and marks on the board, or hides them for a round played unaided
+    fn show_hints(&mut self, show: bool) {
+        self.hints = show;
+        self.board.show_hints(show);
+    }
+
+    // runs the solver on the puzzle and shows what it makes of it, returning the recommended flip
+    fn update(&mut self, puzzle: &vflip::Puzzle, settings: &Settings) -> Option<(usize, usize)>
+    {
+        // count and enumerate the solutions once for every panel
+        let board = &puzzle.board;
+        let aggregate = vflip::aggregate(puzzle, settings.level);
+        let solutions = vfsearch::solutions(puzzle, settings.level, &aggregate);
+
+        // weigh quitting against playing on, if there are few enough solutions to search
+        let level_string = vflevel::prior(settings.level, puzzle).map_or("off".to_owned(), |level| level.to_string());
+        let advice_string = if aggregate.num_solutions <= vfsearch::MAX_SOLUTIONS {
+            vflip::print_advice(&vflip::advise(vflip::coins(board), &solutions, board, settings.budget))
+        }
+        else {
+            "too many solutions to advise".to_owned()
+        };
+        let mut summary = vec![
+            format!("{} solutions, level {}", aggregate.num_solutions, level_string),
+            advice_string
+        ];
+
+        // the cells each flip would settle, if asked, next to the chance of a voltorb
+        if settings.overlay {
+            let settled = vfstrategy::settled(puzzle, &aggregate, &solutions)
+                .unwrap_or_else(|| vec![vec![None; puzzle.columns()]; puzzle.rows()]);
+            self.status.overlay(Some(settled));
+            summary.push(SETTLE_LEGEND.to_owned());
+        }
+        else {
+            self.status.overlay(None);
+        }
+        self.status.update(&aggregate, board, summary);
+
+        // the chance of clearing the board from each flip if the strategy searches, and the flip it chooses
+        let decision = match settings.strategy_name.as_str() {
+            "search" => Some(vfsearch::decide(puzzle, &aggregate, &solutions, settings.budget)),
+            _ => None
+        };
+        let best = match &decision {
+            Some(decision) => vfstrategy::best(&vfstrategy::Decided(decision), puzzle, settings.level, &aggregate),
+            None => vfstrategy::best(settings.strategy.as_ref(), puzzle, settings.level, &aggregate)
+        };
+        match decision {
+            Some(decision) => {
+                let depth_string = format!("strategy search, searched {} of {} ahead", decision.depth, settings.budget.depth);
+                self.rec.update(decision, depth_string);
+            }
+            None => {
+                self.rec.update(vfsearch::Decision::unsearched(board, best), format!("strategy {}", settings.strategy_name));
+            }
+        }
+        self.board.update(puzzle, aggregate, best);
+        self.next = vflipuic::PreviewComponent::new(puzzle.rows(), puzzle.columns());
+        best
+    }
+
+    // lays the panels out side by side if they fit, or one above another, or not at all
+    fn layout(&self, size: (usize, usize)) -> Option<Layout> {
+        self.wide(size).or_else(|| self.stacked(size))
+    }
+
+    // the screen inside the margin, above the log and the prompt, and the log and the prompt
+    fn split(&self, size: (usize, usize)) -> Option<(Rect, Rect, Rect)>
+    {
+        let (prompt, body) = Rect::new(0, 0, size.0, size.1).take_bottom(self.prompt.size().0)?;
+        let (log, body) = body.take_bottom(self.log.size().0)?;
+        let (_, body) = body.take_top(MARGIN.0)?;
+        let (_, body) = body.take_left(MARGIN.1)?;
+        Some((body, log, prompt))
+    }
+
+    // the board and the preview on the left, the solver's panels on the right
+    fn wide(&self, size: (usize, usize)) -> Option<Layout>
+    {
+        let (body, log, prompt) = self.split(size)?;
+        prompt.take_left(self.prompt.size().1)?;
+        let sizes = [self.board.size(), self.next.size(), self.status.size(), self.rec.size()];
+        let left_width = sizes[0].1.max(sizes[1].1);
+        let right_width = sizes[2].1.max(sizes[3].1);
+        let top_height = sizes[0].0.max(sizes[2].0);
+        let (left, right) = body.take_left(left_width + GAP)?;
+        right.take_left(right_width)?;
+        let (board, next) = left.take_top(top_height)?;
+        let (status, rec) = right.take_top(top_height)?;
+        next.take_top(sizes[1].0)?;
+        rec.take_top(sizes[3].0)?;
+        Some(Layout { board, status, rec, next, log, prompt })
+    }
+
+    // every panel in one column, for a narrow terminal
+    fn stacked(&self, size: (usize, usize)) -> Option<Layout>
+    {
+        let (body, log, prompt) = self.split(size)?;
+        prompt.take_left(self.prompt.size().1)?;
+        let sizes = [self.board.size(), self.status.size(), self.rec.size(), self.next.size()];
+        body.take_left(sizes.iter().map(|size| size.1).max().unwrap_or(0))?;
+        let (board, rest) = body.take_top(sizes[0].0 + GAP)?;
+        let (status, rest) = rest.take_top(sizes[1].0 + GAP)?;
+        let (rec, rest) = rest.take_top(sizes[2].0 + GAP)?;
+        let (next, _) = rest.take_top(sizes[3].0)?;
+        Some(Layout { board, status, rec, next, log, prompt })
+    }
+
+    // draws every panel as one frame, or says the screen is too small, returning where to type
+    fn draw(&self, display: &cmdui::UIHandle) -> Result<(usize, usize)>
+    {
+        let size = display.size();
+        let layout = self.layout(size);
+        display.batch(|| {
+            display.clear();
+            match &layout {
+                Some(layout) =>
+                {
+                    self.board.render(display, layout.board)?;
+                    if self.hints {
+                        self.status.render(display, layout.status)?;
+                        self.rec.render(display, layout.rec)?;
+                    }
+                    self.next.render(display, layout.next)?;
+                    self.log.render(display, layout.log)?;
+                    self.prompt.render(display, layout.prompt)?;
+
+                    // put the cursor on the board when it moves over the cells
+                    if let Some((row, column)) = self.board.cursor() {
+                        let loc = self.board.cell_loc(layout.board, row, column);
+                        display.set_cursor(loc.0, loc.1)?;
+                    }
+                    Ok(self.prompt.input(layout.prompt))
+                }
+                None =>
+                {
+                    display.render((0, 0), TOO_SMALL.to_owned())?;
+                    Ok((size.0.saturating_sub(1), 0))
+                }
+            }
+        })
+    }
+
+    // asks a question about a board that would replace the user's, returning the answer
+    fn ask(&mut self, display: &cmdui::UIHandle, question: &str, preview: Option<&vflip::Board>) -> Result<String>
+    {
+        self.prompt.ask(question);
+        self.next.show(preview);
+        let input = self.draw(display)?;
+        let answer = display.text_command(input);
+        self.prompt.ask("");
+        self.next.show(None);
+        answer
+    }
+}
+
+fn main() -> Result<()>
+{
+    // solve without the display, or play a script into one without the terminal, if asked
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("solve") => { std::process::exit(batch::run(&args[2..])); }
+        Some("deal") => { std::process::exit(batch::deal(&args[2..])); }
+        Some("simulate") => { std::process::exit(batch::simulate(&args[2..])); }
+        Some("script") =>
+        {
+            if let Err(error) = script(&args[2..]) {
+                eprintln!("vflip script: {}", error);
+                std::process::exit(batch::EXIT_USAGE);
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
 
     // give setup info
     println!("\nYour board is set up. It looks like this:\n");
-    vflip::print_with_headers(&right, &bottom, &board);
 
     // get the display and the thread
-    let display = cmdui::init(WIDTH, HEIGHT)?;
+    let mut display = cmdui::init()?;
+    run(&display)?;
 
-    // the board
-    display.render((2,3), vflip::print_with_headers(&right, &bottom, &board))?;
-    std::thread::sleep(std::time::Duration::from_millis(100));
+    // put the terminal back for the shell
+    display.shutdown()?;
+    Ok(())
+}
 
-    // print the voltorb status
-    let mut solutions = Vec::new();
-    let mut recommendation: (usize, usize) = (usize::MAX,usize::MAX);
-    vflip::solve(&right, &bottom, board, 0, 0, &mut solutions);
-    let (sol_string, rec_string) = vflip::aggregate(&solutions, &board, &mut recommendation);
-    display.render(STATUS_LOC, sol_string)?;
-    display.render(REC_LOC, rec_string)?;
+// types the keys of a script into a display without the terminal, then prints what it shows;
+// `script [--size rows columns] [file]` reads stdin without a file or with -
+fn script(args: &[String]) -> Result<()>
+{
+    // the size of the screen, if given before the file
+    let (size, args) = match args.first().map(|arg| arg.as_str()) {
+        Some("--size") =>
+        {
+            let number = |index: usize| -> Result<usize> {
+                match args.get(index).ok_or(Error::new("--size needs rows and columns"))?.parse::<usize>()? {
+                    0 => Err(Error::new("--size must be at least one row and one column")),
+                    number => Ok(number)
+                }
+            };
+            ((number(1)?, number(2)?), &args[3..])
+        }
+        _ => (SCRIPT_SIZE, args)
+    };
+
+    // read the script from the file, or stdin
+    let text = match args.first().map(|arg| arg.as_str()) {
+        None | Some("-") =>
+        {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)?;
+            text
+        }
+        Some(path) => std::fs::read_to_string(path)?
+    };
+    let keys = cmdui::Script::parse(&text)?;
+
+    // run the commands until the script quits or runs out of keys
+    let mut display = cmdui::headless(size, Box::new(keys), Box::new(cmdui::Discard))?;
+    let result = run(&display);
+    display.shutdown()?;
+    if result.is_err() && display.next_key().is_some() {
+        return result;
+    }
+
+    // the screen as the last key left it
+    for line in display.snapshot() {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// runs commands on the stock puzzle until quit, showing it and what the solver makes of it
+fn run(display: &cmdui::UIHandle) -> Result<()>
+{
+    // set the base board
+    let mut puzzle = vflip::Puzzle::stock();
+    let mut history = History::new();
+    let mut settings = Settings {
+        level: None,
+        budget: vfsearch::DEFAULT_BUDGET,
+        strategy_name: vfstrategy::NAMES[0].to_owned(),
+        strategy: vfstrategy::by_name(vfstrategy::NAMES[0], vfsearch::DEFAULT_BUDGET)?,
+        overlay: false
+    };
+    let mut screen = Screen::new(&puzzle);
 
     // set data for loop
-    display.set_cursor(CURSOR_DEFAULT.0, CURSOR_DEFAULT.1)?;
-    let mut possibilities = String::new();
+    display.bind(cmdui::Key::Ctrl('z'), "undo");
+    display.bind(cmdui::Key::Ctrl('y'), "redo");
 
     // iterate over every char in stdin
     loop
     {
-        // clear the board
-        display.clear();
+        // show the puzzle and what the solver makes of it
+        let best = screen.update(&puzzle, &settings);
+        let input = screen.draw(display)?;
 
-        // the board
-        display.render(BOARD_LOC, vflip::print_with_headers(&right, &bottom, &board))?;
+        // get a command from the key
+        let command = display.text_command(input)?;
+        match command.as_str() {
+            "quit" => { break; }
+            "undo" => { history.undo(&mut puzzle); }
+            "redo" => { history.redo(&mut puzzle); }
+            "nav" => { navigate(&mut puzzle, &mut history, &settings, display, &mut screen)?; }
 
-        // print the voltorb status
-        let mut solutions = Vec::new();
-        let mut recommendation: (usize, usize) = (usize::MAX,usize::MAX);
-        vflip::solve(&right, &bottom, board, 0, 0, &mut solutions);
-        let (sol_string, rec_string) = vflip::aggregate(&solutions, &board, &mut recommendation);
-        display.render(STATUS_LOC, sol_string)?;
-        display.render(REC_LOC, rec_string)?;
+            // a bare enter or a resize leaves nothing to do
+            command if command.trim().is_empty() => {}
+            _ =>
+            {
+                // remember the puzzle if the command changes it, and log why if it fails
+                let before = puzzle.clone();
+                if let Err(error) = process_command(
+                    &mut puzzle,
+                    &mut settings,
+                    display,
+                    &mut screen,
+                    &command,
+                    &best
+                ) {
+                    screen.log.push(&error.to_string());
+                }
+                if puzzle != before {
+                    history.record(before);
+                }
+            }
+        }
+    }
 
-        
+    Ok(())
+}
 
-        // get a command from the key
-        let command = display.text_command(CURSOR_DEFAULT)?;
-        if command.as_str() == "quit" { break; }
-        match process_command(
-            &mut right,
-            &mut bottom, 
-            &mut board, 
-            &display,
-            &command,
-            &mut possibilities,
-            &recommendation
-        ) 
+
+
+
+// moves over the board with the arrows, setting and marking cells by key
+fn navigate(
+    puzzle: &mut vflip::Puzzle,
+    history: &mut History<vflip::Puzzle>,
+    settings: &Settings,
+    display: &cmdui::UIHandle,
+    screen: &mut Screen
+) -> Result<()>
+{
+    let mut marking = false;
+    let mut solved: Option<vflip::Puzzle> = None;
+    screen.board.show_cursor(true);
+    loop
+    {
+        // show the puzzle with the cursor on the cell, running the solver only if the puzzle changed
+        if solved.as_ref() != Some(&*puzzle) {
+            screen.update(puzzle, settings);
+            solved = Some(puzzle.clone());
+        }
+        screen.prompt.ask(if marking { NAV_MEMO_HINT } else { NAV_HINT });
+        screen.draw(display)?;
+        let cell = screen.board.cursor().unwrap_or((0, 0));
+
+        // let the board move its cursor, or act on the key
+        let key = display.next_key().ok_or(Error::new("No more input"))?;
+        if screen.board.handle_key(key) {
+            continue;
+        }
+        let before = puzzle.clone();
+        match key
         {
-            Ok(_) => {}
-            Err(_) => {}
-        };
+            keyin::Key::Writable('m') => { marking = !marking; }
+            keyin::Key::Writable(c) if c.is_ascii_digit() =>
+            {
+                let value = c.to_digit(10).unwrap_or(0) as u8;
+                if puzzle.values().contains(&value) {
+                    if marking {
+                        puzzle.memo[cell.0][cell.1] ^= vflip::single(value);
+                    }
+                    else {
+                        puzzle.board[cell.0][cell.1] = Some(value);
+                    }
+                }
+            }
+            keyin::Key::Backspace =>
+            {
+                if marking {
+                    puzzle.memo[cell.0][cell.1] = vflip::full(puzzle.max);
+                }
+                else {
+                    puzzle.board[cell.0][cell.1] = None;
+                }
+            }
+            keyin::Key::Resize => { display.resize()?; }
+            keyin::Key::Ctrl('z') => { history.undo(puzzle); continue; }
+            keyin::Key::Ctrl('y') => { history.redo(puzzle); continue; }
+            keyin::Key::Enter | keyin::Key::Escape | keyin::Key::Writable('q') => { break; }
+            _ => {}
+        }
+
+        // remember the puzzle if the key changed it
+        if *puzzle != before {
+            history.record(before);
+        }
     }
 
+    screen.board.show_cursor(false);
+    screen.prompt.ask("");
     Ok(())
 }
 
 
+// plays a dealt round, revealing cells from its hidden board until a voltorb, the last multiplier or quitting
+fn play(
+    puzzle: &mut vflip::Puzzle,
+    round: &vfgen::Round,
+    settings: &Settings,
+    display: &cmdui::UIHandle,
+    screen: &mut Screen
+) -> Result<()>
+{
+    let mut hints = false;
+    let mut solved: Option<vflip::Puzzle> = None;
+    screen.board.show_cursor(true);
+    let ending = loop
+    {
+        // show the puzzle with the cursor on the cell, running the solver only for hints on a changed puzzle
+        if solved.as_ref() != Some(&*puzzle) {
+            if hints {
+                screen.update(puzzle, settings);
+                solved = Some(puzzle.clone());
+            }
+            else {
+                screen.board.show(puzzle);
+            }
+        }
+        screen.show_hints(hints);
+        screen.prompt.ask(PLAY_HINT);
+        screen.draw(display)?;
+        let cell = screen.board.cursor().unwrap_or((0, 0));
+
+        // let the board move its cursor, or act on the key
+        let key = display.next_key().ok_or(Error::new("No more input"))?;
+        if screen.board.handle_key(key) {
+            continue;
+        }
+        match key
+        {
+            keyin::Key::Writable('h') => { hints = !hints; }
+            keyin::Key::Writable(' ') | keyin::Key::Enter =>
+            {
+                // flip the cell if it's still hidden
+                if puzzle.board[cell.0][cell.1].is_some() {
+                    continue;
+                }
+                let value = round.solution[cell.0][cell.1];
+                puzzle.board[cell.0][cell.1] = value;
+                if value == Some(0) {
+                    break format!("Voltorb at {},{}! Round lost, 0 coins", cell.1+1, cell.0+1);
+                }
+                let coins = vflip::coins(&puzzle.board);
+                if vfsearch::cleared(&round.solution, &puzzle.board) {
+                    break format!("Every multiplier found! Round won with {} coins", coins);
+                }
+                screen.log.push(&format!("Flipped {} at {},{}, {} coins", value.unwrap_or(0), cell.1+1, cell.0+1, coins));
+            }
+            keyin::Key::Writable(c) if c.is_ascii_digit() =>
+            {
+                let value = c.to_digit(10).unwrap_or(0) as u8;
+                if puzzle.values().contains(&value) && puzzle.board[cell.0][cell.1].is_none() {
+                    puzzle.memo[cell.0][cell.1] ^= vflip::single(value);
+                }
+            }
+            keyin::Key::Resize => { display.resize()?; }
+            keyin::Key::Escape | keyin::Key::Writable('q') =>
+            {
+                break format!("Quit the round with {} coins", vflip::coins(&puzzle.board));
+            }
+            _ => {}
+        }
+    };
+
+    // the round is over, so show the whole board
+    screen.log.push(&ending);
+    puzzle.board = round.solution.clone();
+    screen.show_hints(true);
+    screen.board.show_cursor(false);
+    screen.prompt.ask("");
+    Ok(())
+}
+
 
 fn process_command(
-    right: &mut vflip::Header,
-    bottom: &mut vflip::Header,
-    board: &mut vflip::Board,
+    puzzle: &mut vflip::Puzzle,
+    settings: &mut Settings,
     display: &cmdui::UIHandle,
-    command: &String,
-    possibilities: &mut String,
-    rec: &(usize, usize)
+    screen: &mut Screen,
+    command: &str,
+    rec: &Option<(usize, usize)>
 ) -> Result<()>
 {
     // turn the command into an iterator
@@ -117,17 +554,7 @@ fn process_command(
         // if the command is help
         "help" =>
         {
-            println!();
-            println!("\tCommands:");
-            println!("help");
-            println!("quit");
-            println!("solve");
-            println!("board");
-            println!("set");
-            println!("reset");
-            println!("clear");
-            println!("headers");
-            println!();
+            screen.log.push(&format!("Commands: {}", COMMANDS.join(" ")));
         }
 
         // if the command is set
@@ -155,16 +582,21 @@ fn process_command(
 
             //println!();
 
+            // check the cell, where 0 stands for every row or column
+            if column as usize > puzzle.columns() || row as usize > puzzle.rows() {
+                return Err(Error::new("Cell out of bounds"));
+            }
+
             // check the value
-            if !vflip::VALS.contains(&value) {
-                display.render(CURSOR_DEFAULT,format!("Invalid Value; must be in {:?}\n", vflip::VALS))?;
+            if !puzzle.values().contains(&value) {
+                screen.log.push(&format!("Invalid Value; must be in {:?}", puzzle.values()));
                 return Ok(());
             }
 
             // create a new board with the value
-            let mut new_board = *board;
+            let mut new_board = puzzle.board.clone();
             let rows = match row {
-                0 => 0..vflip::SIZE,
+                0 => 0..puzzle.rows(),
                 val =>
                 {
                     let index = val as usize;
@@ -172,7 +604,7 @@ fn process_command(
                 }
             };
             let columns = match column {
-                0 => 0..vflip::SIZE,
+                0 => 0..puzzle.columns(),
                 val =>
                 {
                     let index = val as usize;
@@ -187,9 +619,8 @@ fn process_command(
             
 
             // ask the user if they're sure they want to change it
-            display.render(NEXT_LOC, vflip::print(&new_board))?;
-            display.render(QUESTION_LOC, "Are you sure you want to change it? [y|n] ".to_owned())?;
-            let response = display.text_command(CURSOR_DEFAULT)?;
+            let question = change_question(&puzzle.with_board(new_board.clone()));
+            let response = screen.ask(display, &question, Some(&new_board))?;
             //println!();
 
             // act based on response
@@ -197,7 +628,7 @@ fn process_command(
                 "y" =>
                 {
                     // set the board
-                    *board = new_board;
+                    puzzle.board = new_board;
                     //println!("New board set.\n");
                 }
                 _ =>
@@ -207,6 +638,30 @@ fn process_command(
             }
         }
 
+        // fill in the board if only one solution is left
+        "solve" =>
+        {
+            // count the solutions before enumerating any
+            let num_solutions = vflip::count(puzzle, None).num_solutions;
+            if num_solutions != 1 {
+                screen.log.push(&format!("There are {} solutions, not one.", num_solutions));
+                return Ok(());
+            }
+
+            // find the solution
+            let mut new_board = puzzle.board.clone();
+            vflip::solve(puzzle, &mut |solution| { new_board = solution.clone(); });
+
+            // ask the user if they're sure they want to change it
+            let question = change_question(&puzzle.with_board(new_board.clone()));
+            let response = screen.ask(display, &question, Some(&new_board))?;
+
+            // act based on response
+            if response.as_str() == "y" {
+                puzzle.board = new_board;
+            }
+        }
+
         // put a value in the recommended spot
         "rec" =>
         {
@@ -217,26 +672,26 @@ fn process_command(
             }.parse::<u8>()?;
 
             // check the value
-            if !vflip::VALS.contains(&value) {
-                display.render(CURSOR_DEFAULT,format!("Invalid Value; must be in {:?}\n", vflip::VALS))?;
+            if !puzzle.values().contains(&value) {
+                screen.log.push(&format!("Invalid Value; must be in {:?}", puzzle.values()));
                 return Ok(());
             }
 
             // create a new board
-            let mut new_board = *board;
+            let rec = rec.ok_or(Error::new("No recommendation"))?;
+            let mut new_board = puzzle.board.clone();
             new_board[rec.0][rec.1] = Some(value);
 
             // ask the user if they're sure they want to change it
-            display.render(NEXT_LOC, vflip::print(&new_board))?;
-            display.render(QUESTION_LOC, "Are you sure you want to change it? [y|n] ".to_owned())?;
-            let response = display.text_command(CURSOR_DEFAULT)?;
+            let question = change_question(&puzzle.with_board(new_board.clone()));
+            let response = screen.ask(display, &question, Some(&new_board))?;
 
             // act based on response
             match response.as_str() {
                 "y" =>
                 {
                     // set the board
-                    *board = new_board;
+                    puzzle.board = new_board;
                     //println!("New board set.\n");
                 }
                 _ =>
@@ -250,38 +705,33 @@ fn process_command(
         "reset" =>
         {
             // get column
-            vfio::prompt("column = ")?;
-            let column = vfio::get_command()?.parse::<usize>()?;
+            let column = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<usize>()?;
 
             // get row
-            vfio::prompt("row = ")?;
-            let row = vfio::get_command()?.parse::<usize>()?;
+            let row = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<usize>()?;
 
-            println!();
+            // check the cell
+            if !(1..=puzzle.columns()).contains(&column) || !(1..=puzzle.rows()).contains(&row) {
+                return Err(Error::new("Cell out of bounds"));
+            }
 
             // create a new board, setting the cell to none
-            let mut new_board = *board;
-            new_board[vflip::SIZE-row][column-1] = None;
+            let mut new_board = puzzle.board.clone();
+            new_board[row-1][column-1] = None;
 
             // ask the user if they're sure they want to change it
-            println!("Your new board looks like this:\n");
-            vflip::print(&new_board);
-            vfio::prompt("Are you sure you want to change it? [y|n] ")?;
-            let response = vfio::get_command()?;
-            println!();
+            let question = change_question(&puzzle.with_board(new_board.clone()));
+            let response = screen.ask(display, &question, Some(&new_board))?;
 
             // act based on response
-            match response.as_str() {
-                "y" =>
-                {
-                    // set the board
-                    *board = new_board;
-                    println!("New board set.\n");
-                }
-                _ =>
-                {
-                    println!("New board not set.\n");
-                }
+            if response == "y" {
+                puzzle.board = new_board;
             }
         }
 
@@ -289,15 +739,14 @@ fn process_command(
         "clear" =>
         {
             // ask the user if they really want to clear the board
-            display.render(QUESTION_LOC,"Are you sure you want to clear the board? [y|n] ".to_string())?;
-            let response = display.text_command(CURSOR_DEFAULT)?;
+            let response = screen.ask(display, "Are you sure you want to clear the board? [y|n] ", None)?;
 
             // act based on response
             match response.as_str() {
                 "y" =>
                 {
                     // set the board
-                    *board = vflip::init();
+                    *puzzle = vflip::Puzzle::new(puzzle.rows(), puzzle.columns(), puzzle.max);
                     //println!("Board cleared.\n");
                 }
                 _ =>
@@ -307,32 +756,220 @@ fn process_command(
             }
         }
 
+        // mark the values an unopened cell could hold, or unmark it
+        "memo" =>
+        {
+            // get column
+            let column = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<usize>()?;
+
+            // get row
+            let row = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<usize>()?;
+
+            // check the cell
+            if !(1..=puzzle.columns()).contains(&column) || !(1..=puzzle.rows()).contains(&row) {
+                return Err(Error::new("Cell out of bounds"));
+            }
+
+            // get the values it could hold, every value if none are given
+            let domain = match command_iter.next() {
+                None => vflip::full(puzzle.max),
+                Some(values) =>
+                {
+                    let mut domain = 0;
+                    for c in values.chars() {
+                        let value = c.to_digit(10).ok_or(Error::new("Bad Arguments"))? as u8;
+                        if !puzzle.values().contains(&value) {
+                            return Err(Error::new("Bad Arguments"));
+                        }
+                        domain |= vflip::single(value);
+                    }
+                    domain
+                }
+            };
+
+            puzzle.memo[row-1][column-1] = domain;
+        }
+
+        // start an empty puzzle of another size
+        "new" =>
+        {
+            // get the size
+            let rows = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<usize>()?;
+            let columns = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<usize>()?;
+            let max = match command_iter.next() {
+                Some(val) => val.parse::<u8>()?,
+                None => vflip::MAX
+            };
+
+            // check the size
+            if let Some(message) = vflip::check(rows, columns, max) {
+                screen.log.push(&message);
+                return Ok(());
+            }
+
+            *puzzle = vflip::Puzzle::new(rows, columns, max);
+        }
+
+        // deal a round on a level, from a seed if one is given
+        "deal" =>
+        {
+            // get the level and seed
+            let level = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<u8>()?;
+            let seed = match command_iter.next() {
+                Some(val) => Some(val.parse::<u64>()?),
+                None => None
+            };
+
+            // show the puzzle the game would, with the seed to deal it again
+            let round = vfgen::generate(level, seed)?;
+            screen.log.push(&format!("Dealt level {} with seed {}", round.level, round.seed));
+            *puzzle = round.puzzle;
+            settings.level = Some(round.level);
+        }
+
+        // deal a round and play it, flipping cells of the hidden board
+        "play" =>
+        {
+            // get the level and seed
+            let level = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<u8>()?;
+            let seed = match command_iter.next() {
+                Some(val) => Some(val.parse::<u64>()?),
+                None => None
+            };
+
+            // play on the puzzle the game would show
+            let round = vfgen::generate(level, seed)?;
+            screen.log.push(&format!("Playing level {} with seed {}", round.level, round.seed));
+            *puzzle = round.puzzle.clone();
+            settings.level = Some(round.level);
+            play(puzzle, &round, settings, display, screen)?;
+        }
+
+        // set the level the board was dealt on, or turn the prior off
+        "level" =>
+        {
+            // get the level
+            let value = match command_iter.next() {
+                Some("off") => None,
+                Some(val) => Some(val.parse::<u8>()?),
+                None => { return Ok(()); }
+            };
+
+            // check the level
+            if let Some(message) = value.and_then(vflevel::check) {
+                screen.log.push(&message);
+                return Ok(());
+            }
+
+            settings.level = value;
+        }
+
+        // set how many flips ahead and for how many milliseconds the search may look
+        "budget" =>
+        {
+            // get the depth
+            let depth = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<usize>()?;
+
+            // get the time
+            let time = match command_iter.next() {
+                Some(val) => val,
+                None => { return Ok(()); }
+            }.parse::<u64>()?;
+
+            settings.budget = vfsearch::Budget {
+                depth,
+                time: std::time::Duration::from_millis(time)
+            };
+            settings.strategy = vfstrategy::by_name(&settings.strategy_name, settings.budget)?;
+        }
+
+        // choose how the recommended flip is chosen, listing the ways if none is given
+        "strategy" =>
+        {
+            let name = match command_iter.next() {
+                Some(val) => val,
+                None =>
+                {
+                    screen.log.push(&format!("Strategies: {}", vfstrategy::NAMES.join(" ")));
+                    return Ok(());
+                }
+            };
+            settings.strategy = vfstrategy::by_name(name, settings.budget)?;
+            settings.strategy_name = name.to_owned();
+        }
+
+        // show or hide the cells each flip would settle
+        "overlay" =>
+        {
+            settings.overlay = match command_iter.next() {
+                Some("on") => true,
+                Some("off") => false,
+                None => !settings.overlay,
+                Some(_) => { return Err(Error::new("Bad Arguments")); }
+            };
+        }
+
+        // write the puzzle and level to a file
+        "save" =>
+        {
+            let path = command_iter.next().ok_or(Error::new("No file given"))?;
+            vffile::save(path, puzzle, settings.level)?;
+        }
+
+        // replace the puzzle and level with those in a file
+        "load" =>
+        {
+            let path = command_iter.next().ok_or(Error::new("No file given"))?;
+            let (loaded, level) = vffile::load(path)?;
+            *puzzle = loaded;
+            settings.level = level;
+        }
+
         "headers" =>
         {
             // loop over the right header
-            for index in 0..vflip::SIZE
+            for index in 0..puzzle.rows()
             {
                 // ask the user
-                display.render(QUESTION_LOC, format!("Right {} =    ", index+1))?;
-                let num_string = display.text_command(CURSOR_DEFAULT)?;
+                let num_string = screen.ask(display, &format!("Right {} = ", index+1), None)?;
                 let mut nums = num_string
                     .split_whitespace()
                     .map(|string| string.parse::<u8>());
-                right[index].0 = nums.next().ok_or(Error::new("Bad Arguments"))??;
-                right[index].1 = nums.next().ok_or(Error::new("Bad Arguments"))??;
+                puzzle.right[index].0 = nums.next().ok_or(Error::new("Bad Arguments"))??;
+                puzzle.right[index].1 = nums.next().ok_or(Error::new("Bad Arguments"))??;
             }
 
             // loop over the bottom header
-            for index in 0..vflip::SIZE
+            for index in 0..puzzle.columns()
             {
                 // ask the user
-                display.render(QUESTION_LOC, format!("Bottom {} =     ", index+1))?;
-                let num_string = display.text_command(CURSOR_DEFAULT)?;
+                let num_string = screen.ask(display, &format!("Bottom {} = ", index+1), None)?;
                 let mut nums = num_string
                     .split_whitespace()
                     .map(|string| string.parse::<u8>());
-                bottom[index].0 = nums.next().ok_or(Error::new("Bad Arguments"))??;
-                bottom[index].1 = nums.next().ok_or(Error::new("Bad Arguments"))??;
+                puzzle.bottom[index].0 = nums.next().ok_or(Error::new("Bad Arguments"))??;
+                puzzle.bottom[index].1 = nums.next().ok_or(Error::new("Bad Arguments"))??;
             }
 
             // print newline
@@ -345,4 +982,51 @@ fn process_command(
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+
+// asks if the user wants to change the board, warning if the change breaks the headers
+fn change_question(puzzle: &vflip::Puzzle) -> String
+{
+    if vflip::validate(puzzle) {
+        "Are you sure you want to change it? [y|n] ".to_owned()
+    }
+    else {
+        "That contradicts the headers. Are you sure you want to change it? [y|n] ".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // runs a script on a headless display, returning what it shows at the end
+    fn play_script(text: &str) -> Vec<String>
+    {
+        let keys = cmdui::Script::parse(text).unwrap();
+        let mut display = cmdui::headless(SCRIPT_SIZE, Box::new(keys), Box::new(cmdui::Discard)).unwrap();
+        run(&display).unwrap();
+        display.shutdown().unwrap();
+        display.snapshot()
+    }
+
+    #[test]
+    fn script_sets_a_cell() {
+        let snapshot = play_script("set 1 1 3\ny\nquit\n");
+        assert!(snapshot.iter().any(|line| line.trim_start().starts_with("3 - - - -")));
+    }
+
+    #[test]
+    fn script_rejects_a_cell_off_the_board() {
+        let snapshot = play_script("set 9 1 1\nset all 6 1\nset all 1 1\ny\nquit\n");
+        assert_eq!(snapshot.iter().filter(|line| line.trim() == "Cell out of bounds").count(), 2);
+        assert!(snapshot.iter().any(|line| line.trim_start().starts_with("1 1 1 1 1")));
+    }
+
+    #[test]
+    fn script_picks_a_strategy() {
+        let snapshot = play_script("strategy greedy\nquit\n");
+        assert!(snapshot.iter().any(|line| line.trim() == "strategy greedy"));
+    }
+}
@@ -1,7 +1,7 @@
 use std::sync;
 
 // acquires a mutex guard in a single line
-pub fn lock<T>(mutex: &sync::Mutex<T>) -> sync::MutexGuard<T> {
+pub fn lock<T>(mutex: &sync::Mutex<T>) -> sync::MutexGuard<'_, T> {
   match mutex.lock() {
     Ok(guard) => guard,
     Err(error) => error.into_inner()
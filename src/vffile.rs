@@ -0,0 +1,227 @@
+use std::convert::TryFrom;
+use crate::error::*;
+use crate::vflevel;
+use crate::vflip::*;
+
+// writes the puzzle and level in the file format
+pub fn write(puzzle: &Puzzle, level: Option<u8>) -> String
+{
+  let mut print_string = String::new();
+
+  // the shape and level
+  print_string.push_str(format!("size {} {} {}\n", puzzle.rows(), puzzle.columns(), puzzle.max).as_str());
+  match level {
+    Some(level) => { print_string.push_str(format!("level {}\n", level).as_str()); }
+    None => { print_string.push_str("level off\n"); }
+  }
+
+  // the rows with their headers and cells
+  for row in 0..puzzle.rows() {
+    print_string.push_str(format!("row {} {}", puzzle.right[row].0, puzzle.right[row].1).as_str());
+    for cell in &puzzle.board[row] {
+      print_string.push(' ');
+      print_string.push(cell.map_or('-', value_char));
+    }
+    print_string.push('\n');
+  }
+
+  // the column headers
+  for label in &puzzle.bottom {
+    print_string.push_str(format!("column {} {}\n", label.0, label.1).as_str());
+  }
+
+  // the memo marks of the unopened cells
+  for row in 0..puzzle.rows() {
+    for column in 0..puzzle.columns() {
+      let domain = puzzle.memo[row][column];
+      if puzzle.board[row][column].is_none() && domain != full(puzzle.max)
+      {
+        print_string.push_str(format!("memo {} {} ", column+1, row+1).as_str());
+        print_string.extend(members(domain).map(value_char));
+        print_string.push('\n');
+      }
+    }
+  }
+
+  print_string
+}
+
+// reads a puzzle and level from the file format, a statement per line and '#' starting a comment: `size rows columns max`,
+// `level n|off`, `row points voltorbs cells` from the top, `column points voltorbs` from the left, `memo column row values`
+pub fn read(text: &str) -> Result<(Puzzle, Option<u8>)>
+{
+  let mut puzzle: Option<Puzzle> = None;
+  let mut level = None;
+  let mut rows = 0;
+  let mut columns = 0;
+
+  for (index, line) in text.lines().enumerate()
+  {
+    // skip comments and blank lines
+    let line = line.split('#').next().unwrap_or("");
+    let mut words = line.split_whitespace();
+    let keyword = match words.next() {
+      Some(keyword) => keyword,
+      None => { continue; }
+    };
+    let fail = |message: &str| Error::new(format!("Line {}: {}", index+1, message).as_str());
+
+    // every statement but the size needs the puzzle it describes
+    if keyword == "size"
+    {
+      if puzzle.is_some() {
+        return Err(fail("Size given twice"));
+      }
+      let numbers = parse_all(&mut words, 3).ok_or(fail("Size needs rows, columns and max"))?;
+      let (size_rows, size_columns, max) = (numbers[0] as usize, numbers[1] as usize, u8::try_from(numbers[2]).unwrap_or(0));
+      if let Some(message) = check(size_rows, size_columns, max) {
+        return Err(fail(&message));
+      }
+      puzzle = Some(Puzzle::new(size_rows, size_columns, max));
+      continue;
+    }
+    let puzzle = puzzle.as_mut().ok_or(fail("Size must come first"))?;
+
+    match keyword
+    {
+      "level" =>
+      {
+        level = match words.next() {
+          Some("off") => None,
+          Some(word) => Some(word.parse::<u8>().map_err(|_| fail("Bad level"))?),
+          None => { return Err(fail("Level needs a number or off")); }
+        };
+        if let Some(message) = level.and_then(vflevel::check) {
+          return Err(fail(&message));
+        }
+      }
+
+      "row" =>
+      {
+        if rows == puzzle.rows() {
+          return Err(fail("Too many rows"));
+        }
+        puzzle.right[rows] = parse_label(&mut words).ok_or(fail("Row needs points and voltorbs"))?;
+        for column in 0..puzzle.columns() {
+          puzzle.board[rows][column] = match words.next() {
+            Some("-") => None,
+            Some(word) => match word.parse::<u8>() {
+              Ok(value) if puzzle.values().contains(&value) => Some(value),
+              _ => { return Err(fail("Bad cell")); }
+            },
+            None => { return Err(fail("Too few cells")); }
+          };
+        }
+        rows += 1;
+      }
+
+      "column" =>
+      {
+        if columns == puzzle.columns() {
+          return Err(fail("Too many columns"));
+        }
+        puzzle.bottom[columns] = parse_label(&mut words).ok_or(fail("Column needs points and voltorbs"))?;
+        columns += 1;
+      }
+
+      "memo" =>
+      {
+        let numbers = parse_all(&mut words, 2).ok_or(fail("Memo needs a column and row"))?;
+        let (column, row) = (numbers[0] as usize, numbers[1] as usize);
+        if !(1..=puzzle.columns()).contains(&column) || !(1..=puzzle.rows()).contains(&row) {
+          return Err(fail("Cell out of bounds"));
+        }
+        let mut domain = 0;
+        for c in words.next().unwrap_or("").chars() {
+          match c.to_digit(10) {
+            Some(value) if puzzle.values().contains(&(value as u8)) => { domain |= single(value as u8); }
+            _ => { return Err(fail("Bad memo value")); }
+          }
+        }
+        puzzle.memo[row-1][column-1] = domain;
+      }
+
+      _ => { return Err(fail("Unknown statement")); }
+    }
+  }
+
+  // the puzzle must be complete
+  let puzzle = puzzle.ok_or(Error::new("No size given"))?;
+  if rows != puzzle.rows() || columns != puzzle.columns() {
+    return Err(Error::new("Missing rows or columns"));
+  }
+  Ok((puzzle, level))
+}
+
+// parses the next count words as numbers
+fn parse_all<'a>(words: &mut impl Iterator<Item = &'a str>, count: usize) -> Option<Vec<u32>> {
+  (0..count).map(|_| words.next()?.parse::<u32>().ok()).collect()
+}
+
+// parses the points and voltorbs of a header label
+fn parse_label<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<Label> {
+  let numbers = parse_all(words, 2)?;
+  Some((u8::try_from(numbers[0]).ok()?, u8::try_from(numbers[1]).ok()?))
+}
+
+// saves the puzzle and level to a file
+pub fn save(path: &str, puzzle: &Puzzle, level: Option<u8>) -> Result<()> {
+  std::fs::write(path, write(puzzle, level))?;
+  Ok(())
+}
+
+// loads a puzzle and level from a file
+pub fn load(path: &str) -> Result<(Puzzle, Option<u8>)> {
+  read(&std::fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::vfgen;
+
+  // returns true if the puzzle and level survive a write and a read
+  fn round_trips(puzzle: &Puzzle, level: Option<u8>) -> bool {
+    match read(&write(puzzle, level)) {
+      Ok((read, read_level)) => read == *puzzle && read_level == level,
+      Err(_) => false
+    }
+  }
+
+  #[test]
+  fn stock_puzzle_round_trips()
+  {
+    let round = vfgen::generate(3, Some(1)).unwrap();
+    let mut puzzle = round.puzzle.clone();
+    puzzle.board[0][0] = round.solution[0][0];
+    puzzle.board[2][4] = round.solution[2][4];
+    puzzle.memo[1][3] = single(0) | single(2);
+    assert!(round_trips(&puzzle, Some(3)));
+    assert!(round_trips(&puzzle, None));
+  }
+
+  #[test]
+  fn variant_puzzle_round_trips()
+  {
+    let mut puzzle = Puzzle::new(4, 6, 5);
+    puzzle.right = vec![(12, 1), (9, 2), (20, 0), (6, 3)];
+    puzzle.bottom = vec![(8, 1), (7, 1), (10, 2), (9, 0), (6, 1), (7, 1)];
+    puzzle.board[1][5] = Some(5);
+    puzzle.board[3][0] = Some(0);
+    puzzle.memo[0][2] = single(1) | single(4);
+    assert!(round_trips(&puzzle, None));
+  }
+
+  #[test]
+  fn bad_sizes_are_rejected()
+  {
+    let rest = "level off\nrow 0 0 -\ncolumn 0 0\n";
+    assert!(read(rest).is_err());
+    for size in ["size", "size 5 5", "size 0 5 3", "size 5 5 1", "size 5 5 8", "size 100000 100000 3", "size x 5 3"] {
+      assert!(read(&format!("{}\n{}", size, rest)).is_err(), "{}", size);
+    }
+    assert!(read(&format!("size 1 1 3\nsize 1 1 3\n{}", rest)).is_err());
+    assert!(read(&format!("size 1 1 3\n{}", rest)).is_ok());
+  }
+}
@@ -0,0 +1,158 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::error::*;
+use crate::vflevel;
+use crate::vflip::*;
+
+// a small generator of random numbers, kept here so a seed deals the same board on every build
+pub struct Rng {
+  state: u64
+}
+impl Rng
+{
+  // make a generator from a seed
+  pub fn new(seed: u64) -> Self {
+    Rng {
+      state: seed
+    }
+  }
+
+  // the next number, by splitmix64
+  pub fn next_u64(&mut self) -> u64
+  {
+    self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+  }
+
+  // a number below the bound, which must not be zero
+  pub fn below(&mut self, bound: usize) -> usize {
+    (self.next_u64() % bound as u64) as usize
+  }
+}
+
+// a seed from the clock, for a round nobody asked to repeat
+pub fn fresh_seed() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64)
+}
+
+// a dealt round: the board the game hides and the puzzle it shows
+pub struct Round {
+  pub level: u8,
+  pub seed: u64,
+  pub solution: Board,
+  pub puzzle: Puzzle
+}
+
+// deals a round on a level the way the game does, from the seed or a fresh one
+pub fn generate(level: u8, seed: Option<u64>) -> Result<Round>
+{
+  // check the level
+  if let Some(message) = vflevel::check(level) {
+    return Err(Error::new(&message));
+  }
+  let seed = seed.unwrap_or_else(fresh_seed);
+  let mut rng = Rng::new(seed);
+
+  // pick the counts from the level's table
+  let table = &vflevel::LEVELS[level as usize - 1];
+  let (twos, threes, voltorbs) = table[rng.below(table.len())];
+
+  // shuffle the cells, then give the first the voltorbs, the next the twos and threes
+  let mut cells: Vec<(usize, usize)> = (0..SIZE * SIZE).map(|index| (index / SIZE, index % SIZE)).collect();
+  for index in (1..cells.len()).rev() {
+    cells.swap(index, rng.below(index + 1));
+  }
+  let values = std::iter::repeat_n(0, voltorbs as usize)
+    .chain(std::iter::repeat_n(2, twos as usize))
+    .chain(std::iter::repeat_n(3, threes as usize));
+  let mut solution = vec![vec![Some(1); SIZE]; SIZE];
+  for (&(row, column), value) in cells.iter().zip(values) {
+    solution[row][column] = Some(value);
+  }
+
+  // the headers count the points and voltorbs of each line
+  let mut puzzle = Puzzle::stock();
+  for index in 0..SIZE {
+    puzzle.right[index] = label((0..SIZE).map(|column| solution[index][column]));
+    puzzle.bottom[index] = label((0..SIZE).map(|row| solution[row][index]));
+  }
+
+  Ok(Round { level, seed, solution, puzzle })
+}
+
+// the points and voltorbs of a full line
+fn label(line: impl Iterator<Item = Option<u8>>) -> Label
+{
+  let mut label = (0, 0);
+  for value in line.flatten() {
+    label.0 += value;
+    if value == 0 {
+      label.1 += 1;
+    }
+  }
+  label
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  // the twos, threes and voltorbs on a board
+  fn counts(board: &Board) -> (u8, u8, u8)
+  {
+    let mut counts = (0, 0, 0);
+    for value in board.iter().flatten().flatten() {
+      match value {
+        2 => { counts.0 += 1; }
+        3 => { counts.1 += 1; }
+        0 => { counts.2 += 1; }
+        _ => {}
+      }
+    }
+    counts
+  }
+
+  #[test]
+  fn same_seed_deals_same_round()
+  {
+    for level in 1..=8 {
+      let first = generate(level, Some(42)).unwrap();
+      let second = generate(level, Some(42)).unwrap();
+      assert!(first.solution == second.solution && first.puzzle == second.puzzle);
+    }
+    assert!(generate(1, Some(1)).unwrap().solution != generate(1, Some(2)).unwrap().solution);
+  }
+
+  #[test]
+  fn headers_describe_the_hidden_board()
+  {
+    for seed in 0..50 {
+      let round = generate((seed % 8) as u8 + 1, Some(seed)).unwrap();
+      assert!(round.solution.iter().flatten().all(|cell| cell.is_some()));
+      for index in 0..SIZE {
+        assert_eq!(round.puzzle.right[index], label((0..SIZE).map(|column| round.solution[index][column])));
+        assert_eq!(round.puzzle.bottom[index], label((0..SIZE).map(|row| round.solution[row][index])));
+      }
+      assert!(round.puzzle.board.iter().flatten().all(|cell| cell.is_none()));
+    }
+  }
+
+  #[test]
+  fn counts_come_from_the_level_table()
+  {
+    for seed in 0..200 {
+      let level = (seed % 8) as u8 + 1;
+      let round = generate(level, Some(seed)).unwrap();
+      assert!(vflevel::LEVELS[level as usize - 1].contains(&counts(&round.solution)), "level {} seed {}", level, seed);
+    }
+  }
+
+  #[test]
+  fn bad_levels_are_rejected() {
+    assert!(generate(0, Some(1)).is_err());
+    assert!(generate(9, Some(1)).is_err());
+  }
+}
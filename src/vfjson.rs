@@ -0,0 +1,126 @@
+use crate::vflip::*;
+
+// writes the puzzle, its distributions and the recommended flip, counted from 1 like the commands
+pub fn write(puzzle: &Puzzle, level: Option<u8>, aggregate: &Aggregate, best: Option<(usize, usize)>) -> String
+{
+  // what the user entered
+  let right = array(puzzle.right.iter().map(label));
+  let bottom = array(puzzle.bottom.iter().map(label));
+  let board = grid(puzzle, |row, column| puzzle.board[row][column].map_or("null".to_owned(), |value| value.to_string()));
+  let memo = grid(puzzle, |row, column| array(members(puzzle.memo[row][column]).map(|value| value.to_string())));
+  let puzzle_string = format!(
+    "{{\"rows\":{},\"columns\":{},\"max\":{},\"right\":{},\"bottom\":{},\"board\":{},\"memo\":{}}}",
+    puzzle.rows(), puzzle.columns(), puzzle.max, right, bottom, board, memo
+  );
+
+  // what the solver found
+  let probabilities = grid(puzzle, |row, column| {
+    array(puzzle.values().map(|value| number(aggregate.probability(row, column, value))))
+  });
+  let classes = grid(puzzle, |row, column| {
+    if puzzle.board[row][column].is_some() {
+      string("open")
+    }
+    else {
+      string(match aggregate.classify(row, column) {
+        Class::Safe => "safe",
+        Class::Voltorb => "voltorb",
+        Class::One => "one",
+        Class::Uncertain => "unsure"
+      })
+    }
+  });
+  let recommendation = match best {
+    Some((row, column)) => format!("{{\"column\":{},\"row\":{}}}", column+1, row+1),
+    None => "null".to_owned()
+  };
+
+  // why there may be nothing to find
+  let mut messages = contradictions(puzzle);
+  if messages.is_empty() && aggregate.num_solutions == 0 {
+    messages.push("no board fills in the headers".to_owned());
+  }
+  if aggregate.num_solutions > 0 && aggregate.total_weight <= 0.0 {
+    messages.push(format!("no solution could be dealt on level {}", level.unwrap_or(0)));
+  }
+  let contradictions = array(messages.iter().map(|message| string(message)));
+
+  format!(
+    "{{\"puzzle\":{},\"level\":{},\"solutions\":{},\"probabilities\":{},\"classes\":{},\"recommendation\":{},\"contradictions\":{}}}",
+    puzzle_string,
+    level.map_or("null".to_owned(), |level| level.to_string()),
+    aggregate.num_solutions,
+    probabilities,
+    classes,
+    recommendation,
+    contradictions
+  )
+}
+
+// a header label as a pair
+fn label(label: &Label) -> String {
+  format!("[{},{}]", label.0, label.1)
+}
+
+// an array of already written values
+fn array(values: impl Iterator<Item = String>) -> String {
+  format!("[{}]", values.collect::<Vec<String>>().join(","))
+}
+
+// an array of rows of a value written for every cell
+fn grid(puzzle: &Puzzle, cell: impl Fn(usize, usize) -> String) -> String {
+  array((0..puzzle.rows()).map(|row| array((0..puzzle.columns()).map(|column| cell(row, column)))))
+}
+
+// a number, or null for one JSON can't hold
+fn number(value: f64) -> String {
+  if value.is_finite() { value.to_string() } else { "null".to_owned() }
+}
+
+// a string, quoted and escaped
+fn string(text: &str) -> String
+{
+  let mut print_string = String::from('"');
+  for c in text.chars() {
+    match c {
+      '"' => { print_string.push_str("\\\""); }
+      '\\' => { print_string.push_str("\\\\"); }
+      c if (c as u32) < 32 => { print_string.push_str(format!("\\u{:04x}", c as u32).as_str()); }
+      c => { print_string.push(c); }
+    }
+  }
+  print_string.push('"');
+  print_string
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  #[test]
+  fn strings_are_escaped() {
+    assert_eq!(string("plain"), "\"plain\"");
+    assert_eq!(string("say \"hi\""), "\"say \\\"hi\\\"\"");
+    assert_eq!(string("a\\b"), "\"a\\\\b\"");
+    assert_eq!(string("line\nbreak\ttab\u{1f}"), "\"line\\u000abreak\\u0009tab\\u001f\"");
+    assert_eq!(string("é"), "\"é\"");
+  }
+
+  #[test]
+  fn non_finite_numbers_are_null() {
+    assert_eq!(number(0.25), "0.25");
+    assert_eq!(number(f64::NAN), "null");
+    assert_eq!(number(f64::INFINITY), "null");
+    assert_eq!(number(f64::NEG_INFINITY), "null");
+  }
+
+  #[test]
+  fn unsolvable_puzzle_writes_only_numbers() {
+    let mut puzzle = Puzzle::stock();
+    puzzle.right[0] = (20, 0);
+    let json = write(&puzzle, Some(1), &aggregate(&puzzle, Some(1)), None);
+    assert!(!json.contains("NaN") && !json.contains("inf"));
+    assert!(json.contains("\"solutions\":0"));
+  }
+}
@@ -0,0 +1,91 @@
+use crate::vflip::*;
+
+// constants
+pub const NUM_LEVELS: u8 = 8;
+
+// the (twos, threes, voltorbs) the game may deal on each level, each equally likely
+pub const LEVELS: [[(u8, u8, u8); 5]; NUM_LEVELS as usize] = [
+  [(3,1,6), (0,3,6), (5,0,6), (2,2,6), (4,1,6)],
+  [(1,3,7), (6,0,7), (3,2,7), (0,4,7), (5,1,7)],
+  [(2,3,8), (7,0,8), (4,2,8), (1,4,8), (6,1,8)],
+  [(3,3,8), (0,5,8), (8,0,10), (5,2,10), (2,4,10)],
+  [(7,1,10), (4,3,10), (1,5,10), (9,0,10), (6,2,10)],
+  [(3,4,10), (0,6,10), (8,1,10), (5,3,10), (2,5,10)],
+  [(7,2,10), (4,4,10), (1,6,13), (9,1,13), (6,3,10)],
+  [(0,7,10), (8,2,10), (5,4,10), (2,6,10), (7,3,10)]
+];
+
+// returns an error message if the level isn't in the tables
+pub fn check(level: u8) -> Option<String> {
+  if level == 0 || level > NUM_LEVELS {
+    return Some(format!("Invalid level; must be in 1..={}", NUM_LEVELS));
+  }
+  None
+}
+
+// n! as a float, which holds the board sized factorials comfortably
+fn factorial(n: usize) -> f64 {
+  (1..=n).map(|index| index as f64).product()
+}
+
+// the number of distinct boards with the given counts of twos, threes and voltorbs
+fn arrangements(twos: u8, threes: u8, voltorbs: u8) -> f64
+{
+  let cells = SIZE * SIZE;
+  let ones = cells - twos as usize - threes as usize - voltorbs as usize;
+  factorial(cells) / (factorial(twos as usize) * factorial(threes as usize) * factorial(voltorbs as usize) * factorial(ones))
+}
+
+// the probability of the game dealing one particular board with the given counts
+pub fn weight(level: u8, twos: u8, threes: u8, voltorbs: u8) -> f64
+{
+  // count how many entries in the level table deal these counts
+  let table = &LEVELS[level as usize - 1];
+  let matches = table
+    .iter()
+    .filter(|&&entry| entry == (twos, threes, voltorbs))
+    .count();
+  if matches == 0 {
+    return 0.0;
+  }
+
+  // each entry is equally likely and deals each of its arrangements uniformly
+  matches as f64 / table.len() as f64 / arrangements(twos, threes, voltorbs)
+}
+
+// the weight of a full board from its totals, since its points and voltorbs fix every count but the threes
+pub fn weight_by_threes(level: u8, points: u32, voltorbs: u32, threes: u32) -> f64
+{
+  // ones + twos + threes + voltorbs = cells and ones + 2 twos + 3 threes = points
+  let cells = (SIZE * SIZE) as i64;
+  let twos = points as i64 + voltorbs as i64 - cells - 2 * threes as i64;
+  let ones = cells - voltorbs as i64 - twos - threes as i64;
+  if twos < 0 || ones < 0 {
+    return 0.0;
+  }
+  weight(level, twos as u8, threes as u8, voltorbs as u8)
+}
+
+// the level whose deal tables apply to the puzzle, none for variants the game never deals
+pub fn prior(level: Option<u8>, puzzle: &Puzzle) -> Option<u8> {
+  level.filter(|_| puzzle.is_stock())
+}
+
+// how likely the game is to deal a full board, or 1 without a level
+pub fn board_weight(level: Option<u8>, board: &Board) -> f64
+{
+  let level = match level {
+    Some(level) => level,
+    None => { return 1.0; }
+  };
+
+  // count the special values on the board
+  let mut counts = [0; MAX_VALUE as usize + 1];
+  for row in board {
+    for value in row.iter().flatten() {
+      counts[*value as usize] += 1;
+    }
+  }
+
+  weight(level, counts[2], counts[3], counts[0])
+}
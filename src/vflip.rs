@@ -1,149 +1,474 @@
-
 // uses
-use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::time::Instant;
+use crate::{vflevel, vfsearch, vfstrategy};
 
 // constants
 pub const SIZE: usize = 5;
-pub const VALS: [u8; 4] = [0,1,2,3];
+pub const MAX: u8 = 3;
+pub const MAX_VALUE: u8 = 7;
+pub const MAX_SIDE: usize = 64;
 
 // custom types for application
-pub type Board = [[Option<u8>; SIZE]; SIZE];
+pub type Board = Vec<Vec<Option<u8>>>;
 pub type Label = (u8,u8);
-pub type Header = [Label; SIZE];
+pub type Header = Vec<Label>;
+
+// the values a cell may still hold, one bit per value
+pub type Domain = u8;
+pub type Domains = Vec<Vec<Domain>>;
+
+// the values the user has marked each unopened cell as possibly holding
+pub type Memo = Domains;
+
+// a puzzle of any size, with multipliers from two up to its max
+#[derive(Clone, PartialEq)]
+pub struct Puzzle {
+  pub right: Header,
+  pub bottom: Header,
+  pub board: Board,
+  pub memo: Memo,
+  pub max: u8
+}
+impl Puzzle
+{
+  // makes an empty puzzle with zeroed headers
+  pub fn new(rows: usize, columns: usize, max: u8) -> Self {
+    Puzzle {
+      right: vec![(0,0); rows],
+      bottom: vec![(0,0); columns],
+      board: init(rows, columns),
+      memo: vec![vec![full(max); columns]; rows],
+      max
+    }
+  }
+
+  // makes an empty puzzle the size of the real game
+  pub fn stock() -> Self {
+    Puzzle::new(SIZE, SIZE, MAX)
+  }
+
+  // the number of rows
+  pub fn rows(&self) -> usize {
+    self.right.len()
+  }
+
+  // the number of columns
+  pub fn columns(&self) -> usize {
+    self.bottom.len()
+  }
+
+  // the values a cell can hold
+  pub fn values(&self) -> RangeInclusive<u8> {
+    0..=self.max
+  }
+
+  // returns true if the puzzle is the size the real game deals
+  pub fn is_stock(&self) -> bool {
+    self.rows() == SIZE && self.columns() == SIZE && self.max == MAX
+  }
+
+  // the same puzzle with a different board
+  pub fn with_board(&self, board: Board) -> Self {
+    Puzzle { board, ..self.clone() }
+  }
+}
+
+// returns an error message if a puzzle can't be made with the size
+pub fn check(rows: usize, columns: usize, max: u8) -> Option<String> {
+  if !(1..=MAX_SIDE).contains(&rows) || !(1..=MAX_SIDE).contains(&columns) || !(2..=MAX_VALUE).contains(&max) {
+    return Some(format!("Invalid size; needs 1..={} rows and columns and a max in 2..={}", MAX_SIDE, MAX_VALUE));
+  }
+  None
+}
 
 // init the game board
-pub fn init() -> Board
+pub fn init(rows: usize, columns: usize) -> Board
+{
+  vec![vec![None;columns];rows]
+}
+
+// the domain of a cell that could hold anything up to the max
+pub fn full(max: u8) -> Domain {
+  ((1u16 << (max + 1)) - 1) as Domain
+}
+
+// the domain holding only the value
+pub fn single(value: u8) -> Domain {
+  1 << value
+}
+
+// returns true if the value is in the domain
+pub fn contains(domain: Domain, value: u8) -> bool {
+  domain & single(value) != 0
+}
+
+// the values in the domain, from lowest to highest
+pub fn members(domain: Domain) -> impl Iterator<Item = u8> {
+  (0..=MAX_VALUE).filter(move |&value| contains(domain, value))
+}
+
+// returns the value if the domain holds exactly one
+pub fn fixed(domain: Domain) -> Option<u8> {
+  if domain.count_ones() == 1 { Some(domain.trailing_zeros() as u8) } else { None }
+}
+
+// the domains allowed by the revealed cells of the board and the memo marks of the rest
+pub fn domains(puzzle: &Puzzle) -> Domains
 {
-  [[None;SIZE];SIZE]
+  let mut domains = puzzle.memo.clone();
+  for row in 0..puzzle.rows() {
+    for column in 0..puzzle.columns() {
+      if let Some(value) = puzzle.board[row][column] {
+        domains[row][column] = single(value);
+      }
+    }
+  }
+  domains
 }
 
-// validate the board
-pub fn validate(right: &Header, bottom: &Header, board: &Board) -> bool
+// the header and the cells of every row followed by every column
+fn lines(puzzle: &Puzzle) -> Vec<(Label, Vec<(usize, usize)>)>
 {
+  let mut lines = Vec::with_capacity(puzzle.rows() + puzzle.columns());
+  for row in 0..puzzle.rows() {
+    let cells = (0..puzzle.columns()).map(|column| (row, column)).collect();
+    lines.push((puzzle.right[row], cells));
+  }
+  for column in 0..puzzle.columns() {
+    let cells = (0..puzzle.rows()).map(|row| (row, column)).collect();
+    lines.push((puzzle.bottom[column], cells));
+  }
+  lines
+}
 
-  // make sure each row is valid
-  for row in 0..SIZE
+// the (min points, max points, min voltorbs, max voltorbs) a domain can contribute to a line
+fn bounds(domain: Domain) -> (u32, u32, u32, u32)
+{
+  let mut bounds = (u32::MAX, 0, 1, 0);
+  for value in members(domain) {
+    let voltorbs = (value == 0) as u32;
+    bounds.0 = bounds.0.min(value as u32);
+    bounds.1 = bounds.1.max(value as u32);
+    bounds.2 = bounds.2.min(voltorbs);
+    bounds.3 = bounds.3.max(voltorbs);
+  }
+  bounds
+}
+
+// removes the values of a line's cells that can't meet its header, None on a contradiction
+fn prune_line(label: Label, cells: &[(usize, usize)], domains: &mut Domains) -> Option<bool>
+{
+  // sum the bounds over the line
+  let mut cell_bounds = Vec::with_capacity(cells.len());
+  let mut totals = (0, 0, 0, 0);
+  for &(row, column) in cells
   {
-    // init the sum of the voltorbs 
-    let mut num_points = 0;
-    let mut num_voltorbs = 0;
-    let mut num_none = 0;
+    if domains[row][column] == 0 {
+      return None;
+    }
+    let bounds = bounds(domains[row][column]);
+    cell_bounds.push(bounds);
+    totals = (totals.0 + bounds.0, totals.1 + bounds.1, totals.2 + bounds.2, totals.3 + bounds.3);
+  }
 
-    // iterate over the columns
-    for column in 0..SIZE
+  // check that the header is reachable at all
+  let (points, voltorbs) = (label.0 as u32, label.1 as u32);
+  if points < totals.0 || points > totals.1 || voltorbs < totals.2 || voltorbs > totals.3 {
+    return None;
+  }
+
+  // keep only the values that let the rest of the line reach the header
+  let mut changed = false;
+  for (index, &(row, column)) in cells.iter().enumerate()
+  {
+    let bounds = cell_bounds[index];
+    let mut domain = domains[row][column];
+    for value in members(domain)
     {
-      // take action depending on the value in the cell
-      match board[row][column] {
-        Some(0) => { num_voltorbs += 1; }
-        Some(points) => { num_points += points; }
-        None => { num_none += 1; }
+      let is_voltorb = (value == 0) as u32;
+      let reachable =
+        totals.0 - bounds.0 + value as u32 <= points &&
+        totals.1 - bounds.1 + value as u32 >= points &&
+        totals.2 - bounds.2 + is_voltorb <= voltorbs &&
+        totals.3 - bounds.3 + is_voltorb >= voltorbs;
+      if !reachable {
+        domain &= !single(value);
       }
     }
+    if domain != domains[row][column] {
+      domains[row][column] = domain;
+      changed = true;
+    }
+  }
+
+  Some(changed)
+}
 
-    // check if the row is valid
-    if num_none > 0 {
-      if num_points > right[row].0 || num_voltorbs > right[row].1 {
-        return false;
+// narrows the domains until every line agrees with its header, false on a contradiction
+pub fn propagate(puzzle: &Puzzle, domains: &mut Domains) -> bool
+{
+  let lines = lines(puzzle);
+  let mut changed = true;
+  while changed
+  {
+    changed = false;
+    for (label, cells) in &lines {
+      match prune_line(*label, cells, domains) {
+        Some(line_changed) => { changed |= line_changed; }
+        None => { return false; }
       }
     }
-    else {
-      if num_points != right[row].0 || num_voltorbs != right[row].1 {
-        return false;
+  }
+  true
+}
+
+// describes what in the puzzle can't hold, line by line, without solving it
+pub fn contradictions(puzzle: &Puzzle) -> Vec<String>
+{
+  let mut contradictions = Vec::new();
+
+  // every line must be able to meet its own header
+  let domains = domains(puzzle);
+  for (index, (label, cells)) in lines(puzzle).iter().enumerate() {
+    if prune_line(*label, cells, &mut domains.clone()).is_none()
+    {
+      let name = if index < puzzle.rows() {
+        format!("row {}", index + 1)
       }
+      else {
+        format!("column {}", index - puzzle.rows() + 1)
+      };
+      contradictions.push(format!("{} can't meet its header", name));
     }
-    
   }
 
-  // make sure each column is valid
-  for column in 0..SIZE
-  {
-    // init the sum of the voltorbs 
-    let mut num_points = 0;
-    let mut num_voltorbs = 0;
-    let mut num_none = 0;
+  // the rows and the columns cover the same cells
+  let sum = |header: &Header, points: bool| -> u32 {
+    header.iter().map(|label| if points { label.0 } else { label.1 } as u32).sum()
+  };
+  if sum(&puzzle.right, true) != sum(&puzzle.bottom, true) {
+    contradictions.push("the rows and columns disagree on points".to_owned());
+  }
+  if sum(&puzzle.right, false) != sum(&puzzle.bottom, false) {
+    contradictions.push("the rows and columns disagree on voltorbs".to_owned());
+  }
 
-    // iterate over the columns
-    for row in 0..SIZE
-    {
-      // take action depending on the value in the cell
-      match board[row][column]{
-        Some(0) => { num_voltorbs += 1; }
-        Some(points) => { num_points += points; }
-        None => { num_none += 1; }
+  contradictions
+}
+
+// validate the board, checking both the upper and lower bounds of every line
+pub fn validate(puzzle: &Puzzle) -> bool {
+  propagate(puzzle, &mut domains(puzzle))
+}
+
+// solves the puzzle, visiting every board that fills in the game board
+pub fn solve<F: FnMut(&Board)>(puzzle: &Puzzle, visit: &mut F)
+{
+  let mut domains = domains(puzzle);
+  if propagate(puzzle, &mut domains) {
+    search(puzzle, domains, visit);
+  }
+}
+
+// branches on the least certain cell of propagated domains
+fn search<F: FnMut(&Board)>(puzzle: &Puzzle, domains: Domains, visit: &mut F)
+{
+  // find the open cell with the fewest values left
+  let mut branch: Option<(usize, usize)> = None;
+  let mut fewest = u32::MAX;
+  for row in 0..puzzle.rows() {
+    for column in 0..puzzle.columns() {
+      let size = domains[row][column].count_ones();
+      if size > 1 && size < fewest {
+        branch = Some((row, column));
+        fewest = size;
       }
     }
+  }
+
+  // base case: every cell is forced
+  let (row, column) = match branch {
+    Some(cell) => cell,
+    None =>
+    {
+      let board: Board = domains
+        .iter()
+        .map(|row| row.iter().map(|&domain| fixed(domain)).collect())
+        .collect();
+      visit(&board);
+      return;
+    }
+  };
 
-    // check if the row is valid
-    if num_none > 0 {
-      if num_points > bottom[column].0 || num_voltorbs > bottom[column].1 {
-        return false;
+  // try every value left in the cell
+  for value in members(domains[row][column])
+  {
+    let mut next = domains.clone();
+    next[row][column] = single(value);
+    if propagate(puzzle, &mut next) {
+      search(puzzle, next, visit);
+    }
+  }
+}
+
+// the column sums of the rows counted so far, followed by the number of threes among them,
+// wide enough that adding a row to a sum near a header's limit can't overflow
+type CountState = Vec<u16>;
+
+// every assignment of a row's domains that meets its header exactly
+fn row_assignments(label: Label, domains: &[Domain]) -> Vec<Vec<u8>>
+{
+  // extend a partial assignment one column at a time
+  fn extend(label: Label, domains: &[Domain], points: u16, voltorbs: u8, assignment: &mut Vec<u8>, assignments: &mut Vec<Vec<u8>>)
+  {
+    let column = assignment.len();
+    if column == domains.len() {
+      if (points, voltorbs) == (label.0 as u16, label.1) {
+        assignments.push(assignment.clone());
       }
+      return;
     }
-    else {
-      if num_points != bottom[column].0 || num_voltorbs != bottom[column].1 {
-        return false;
+    for value in members(domains[column])
+    {
+      let next = (points + value as u16, voltorbs + (value == 0) as u8);
+      if next.0 <= label.0 as u16 && next.1 <= label.1 {
+        assignment.push(value);
+        extend(label, domains, next.0, next.1, assignment, assignments);
+        assignment.pop();
       }
     }
-    
   }
 
-  // if no issues were found, return true
-  return true;
+  let mut assignments = Vec::new();
+  extend(label, domains, 0, 0, &mut Vec::with_capacity(domains.len()), &mut assignments);
+  assignments
 }
 
-// recursively solves the puzzle
-pub fn solve(right: &Header, bottom: &Header, mut board: Board, row: usize, column: usize, solutions: &mut Vec<Board>)
+// adds a row assignment to a state, None if it overshoots the bottom header
+fn advance(bottom: &Header, state: &CountState, assignment: &[u8], count_threes: bool) -> Option<CountState>
 {
-  // base case
-  if row >= SIZE || column >= SIZE
+  let mut next = state.clone();
+  for column in 0..bottom.len()
   {
-    // add the board to the solutions
-    solutions.push(board);
-
-    // print the solution
-    //print_with_headers(right, bottom, &board);
+    let value = assignment[column];
+    if value == 0 { next[2 * column + 1] += 1; } else { next[2 * column] += value as u16; }
+    if next[2 * column] > bottom[column].0 as u16 || next[2 * column + 1] > bottom[column].1 as u16 {
+      return None;
+    }
+    if count_threes && value == 3 {
+      next[2 * bottom.len()] += 1;
+    }
+  }
+  Some(next)
+}
 
-    // break out of the function
-    return;
+// counts the solutions and weighs every cell's values by dynamic programming over the rows
+pub fn count(puzzle: &Puzzle, level: Option<u8>) -> Aggregate
+{
+  let (rows, columns) = (puzzle.rows(), puzzle.columns());
+  let level = vflevel::prior(level, puzzle);
+  let mut aggregate = Aggregate {
+    num_solutions: 0,
+    total_weight: 0.0,
+    weights: vec![vec![vec![0.0; puzzle.max as usize + 1]; columns]; rows],
+    recommendation: None
+  };
+
+  // narrow the domains before building the rows
+  let mut domains = domains(puzzle);
+  if !propagate(puzzle, &mut domains) {
+    return aggregate;
+  }
+  let assignments: Vec<Vec<Vec<u8>>> = (0..rows)
+    .map(|row| row_assignments(puzzle.right[row], &domains[row]))
+    .collect();
+
+  // count the ways to reach every state from the top, tracking threes only for a level's prior,
+  // saturating since a large variant can have more solutions than fit
+  let bottom = &puzzle.bottom;
+  let count_threes = level.is_some();
+  let start: CountState = vec![0; 2 * columns + 1];
+  let mut forward: Vec<HashMap<CountState, u64>> = vec![HashMap::new(); rows + 1];
+  forward[0].insert(start.clone(), 1);
+  for row in 0..rows {
+    let (above, below) = forward.split_at_mut(row + 1);
+    for (state, &ways) in &above[row] {
+      for assignment in &assignments[row] {
+        if let Some(next) = advance(bottom, state, assignment, count_threes) {
+          let total = below[0].entry(next).or_insert(0);
+          *total = total.saturating_add(ways);
+        }
+      }
+    }
   }
 
-  // check of the cell already has a value
-  match board[row][column] {
-    None =>
+  // the weight of finishing in a state, where the threes fix every other count of the board
+  let total_points: u32 = bottom.iter().map(|label| label.0 as u32).sum();
+  let total_voltorbs: u32 = bottom.iter().map(|label| label.1 as u32).sum();
+  let complete = |state: &CountState| {
+    (0..columns).all(|column| (state[2 * column], state[2 * column + 1]) == (bottom[column].0 as u16, bottom[column].1 as u16))
+  };
+  let finish = |state: &CountState| -> f64
+  {
+    match (complete(state), level) {
+      (false, _) => 0.0,
+      (true, None) => 1.0,
+      (true, Some(level)) => vflevel::weight_by_threes(level, total_points, total_voltorbs, state[2 * columns] as u32)
+    }
+  };
+
+  // weigh the ways to finish the board from every reachable state, from the bottom up
+  let mut backward: Vec<HashMap<CountState, f64>> = vec![HashMap::new(); rows + 1];
+  for (state, &ways) in &forward[rows] {
+    if complete(state) {
+      aggregate.num_solutions = aggregate.num_solutions.saturating_add(ways);
+    }
+    backward[rows].insert(state.clone(), finish(state));
+  }
+  for row in (0..rows).rev() {
+    for state in forward[row].keys()
     {
-      // try all values of board
-      for val in VALS
+      let mut weight = 0.0;
+      for assignment in &assignments[row] {
+        if let Some(next) = advance(bottom, state, assignment, count_threes) {
+          weight += backward[row + 1].get(&next).copied().unwrap_or(0.0);
+        }
+      }
+      backward[row].insert(state.clone(), weight);
+    }
+  }
+  aggregate.total_weight = backward[0].get(&start).copied().unwrap_or(0.0);
+
+  // pair every prefix with every suffix through each row's assignments
+  for row in 0..rows {
+    for (state, &ways) in &forward[row] {
+      for assignment in &assignments[row]
       {
-        // set the cell in the board
-        board[row][column] = Some(val);
-
-        // validate the board
-        if validate(right, bottom, &board)
-        {
-          // print the intermediate board
-          //print_with_headers(right, bottom, &board);
-
-          // recursive call
-          let next_column = (column + 1) % SIZE;
-          let next_row = if next_column < column { row + 1 } else { row };
-          solve(right, bottom, board, next_row, next_column, solutions);
+        let weight = match advance(bottom, state, assignment, count_threes) {
+          Some(next) => ways as f64 * backward[row + 1].get(&next).copied().unwrap_or(0.0),
+          None => 0.0
+        };
+        for column in 0..columns {
+          aggregate.weights[row][column][assignment[column] as usize] += weight;
         }
       }
-    },
-    Some(_) =>
-    {
-      // recursive call
-      let next_column = (column + 1) % SIZE;
-      let next_row = if next_column < column { row + 1 } else { row };
-      return solve(right, bottom, board, next_row, next_column, solutions);
     }
   }
+
+  aggregate
 }
 
+// the char drawn for a revealed value
+pub fn value_char(value: u8) -> char {
+  char::from_digit(value as u32, 10).unwrap_or('?')
+}
 
 // prints the board to the screen
-pub fn print_with_headers(right: &Header, bottom: &Header, board: &Board) -> String
+pub fn print_with_headers(puzzle: &Puzzle, aggregate: Option<&Aggregate>) -> String
 {
+  let Puzzle { right, bottom, board, memo, max } = puzzle;
 
   // create a print string
   let mut print_string = String::new();
@@ -156,12 +481,11 @@ pub fn print_with_headers(right: &Header, bottom: &Header, board: &Board) -> Str
 
       // push the char to the string
       print_string.push(match board[row][column] {
-        Some(0) => '0',
-        Some(1) => '1',
-        Some(2) => '2',
-        Some(3) => '3',
-        Some(_) => '?',
-        None => '-'
+        Some(value) => value_char(value),
+        None => match aggregate {
+          Some(aggregate) => aggregate.classify(row, column).glyph(),
+          None => '-'
+        }
       });
 
       // add a space at the end
@@ -176,7 +500,7 @@ pub fn print_with_headers(right: &Header, bottom: &Header, board: &Board) -> Str
   }
 
   // add the headers to the bottom
-  print_string.push_str("\n");
+  print_string.push('\n');
   for label in bottom {
     print_string.push_str(format!("{:0>2} ", label.0).as_str());
   }
@@ -185,9 +509,26 @@ pub fn print_with_headers(right: &Header, bottom: &Header, board: &Board) -> Str
     print_string.push_str(format!(" {: >1} ", label.1).as_str());
   }
 
+  // list the memo marks of the unopened cells by column and row
+  let mut memo_string = String::new();
+  for row in 0..board.len() {
+    for column in 0..board[row].len() {
+      if board[row][column].is_none() && memo[row][column] != full(*max)
+      {
+        memo_string.push_str(format!(" {},{}:", column+1, row+1).as_str());
+        for value in members(memo[row][column]) {
+          memo_string.push(value_char(value));
+        }
+      }
+    }
+  }
+  if !memo_string.is_empty() {
+    print_string.push_str(format!("\nmemo{}", memo_string).as_str());
+  }
+
   // print the string
   //println!("{}", print_string);
-  return print_string;
+  print_string
 }
 
 // prints the board to the screen
@@ -205,11 +546,7 @@ pub fn print(board: &Board) -> String
 
       // push the char to the string
       print_string.push(match board[row][column] {
-        Some(0) => '0',
-        Some(1) => '1',
-        Some(2) => '2',
-        Some(3) => '3',
-        Some(_) => '?',
+        Some(value) => value_char(value),
         None => '-'
       });
 
@@ -223,153 +560,328 @@ pub fn print(board: &Board) -> String
 
   // print the string
   //println!("{}", print_string);
-  return print_string;
+  print_string
 }
 
 
 
-// do useful aggregation on the boards
-pub fn aggregate(boards: &Vec<Board>, game_board: &Board, recommendation: &mut (usize, usize)) -> (String, String) {
+// the distribution of values over every cell of the solutions
+pub struct Aggregate {
+  pub num_solutions: u64,
+  pub total_weight: f64,
+  pub weights: Vec<Vec<Vec<f64>>>,
+  pub recommendation: Option<(usize, usize)>
+}
+impl Aggregate
+{
+  // the number of rows
+  pub fn rows(&self) -> usize {
+    self.weights.len()
+  }
 
-  // get the number of voltorbs
-  let mut num_voltorbs: [[u32;SIZE];SIZE] = [[0;SIZE];SIZE];
-  let mut num_multipliers: [[u32;SIZE];SIZE] = [[0;SIZE];SIZE];
+  // the number of columns
+  pub fn columns(&self) -> usize {
+    self.weights.first().map_or(0, |row| row.len())
+  }
 
-  // iterate over every board in the vector
-  for board in boards {
-    for row in 0..SIZE {
-      for column in 0..SIZE
-      {
-        // get the value of the board
-        match board[row][column] {
-          None => {}
-          Some(val) =>
-          {
-            // if the value is zero
-            if val == 0 {
-              num_voltorbs[row][column] += 1;
-            }
+  // the probability that a cell holds a value
+  pub fn probability(&self, row: usize, column: usize, value: u8) -> f64
+  {
+    if self.total_weight <= 0.0 {
+      return 0.0;
+    }
+    self.weights[row][column].get(value as usize).copied().unwrap_or(0.0) / self.total_weight
+  }
 
-            // if the value is greater than one
-            if val > 1 {
-              num_multipliers[row][column] += 1;
-            }
-          }
-        }
-      }
+  // returns true if some likely solution has the value in the cell
+  pub fn possible(&self, row: usize, column: usize, value: u8) -> bool {
+    self.weights[row][column].get(value as usize).is_some_and(|&weight| weight > 0.0)
+  }
+
+  // the probability that a cell holds a multiplier
+  pub fn multiplier(&self, row: usize, column: usize) -> f64 {
+    (2..self.weights[row][column].len()).map(|value| self.probability(row, column, value as u8)).sum()
+  }
+
+  // returns true if some solution has a multiplier in the cell
+  pub fn may_multiply(&self, row: usize, column: usize) -> bool {
+    (2..self.weights[row][column].len()).any(|value| self.possible(row, column, value as u8))
+  }
+
+  // classifies a cell by the values its solutions allow
+  pub fn classify(&self, row: usize, column: usize) -> Class
+  {
+    let voltorb = self.possible(row, column, 0);
+    let one = self.possible(row, column, 1);
+    let multiplier = self.may_multiply(row, column);
+    match (voltorb, one, multiplier) {
+      (false, true, false) => Class::One,
+      (true, false, false) => Class::Voltorb,
+      (false, _, true) => Class::Safe,
+      _ => Class::Uncertain
     }
   }
+}
 
-  // print the number of voltorbs
-  //println!("The number of possible tables that have a voltorb in each cell:\n");
-  let mut num_voltorbs_string = String::new();
-  for row in 0..SIZE {
-    for column in 0..SIZE
-    {
-      // if the game board spot is filled, don't add anything
-      if game_board[row][column] != None || num_multipliers[row][column] < 1
-      {
-        num_voltorbs_string.push_str(format!("{: >4}", '-').as_str());
-      }
-      else
-      {
-        num_voltorbs_string.push_str(format!("{: >4}", num_voltorbs[row][column]).as_str());
-      }
-      num_voltorbs_string.push(' ');
+// what every solution agrees on about an unopened cell
+#[derive(Clone, Copy, PartialEq)]
+pub enum Class {
+  Safe,
+  Voltorb,
+  One,
+  Uncertain
+}
+impl Class
+{
+  // the glyph drawn over an unopened cell
+  pub fn glyph(&self) -> char {
+    match self {
+      Class::Safe => '+',
+      Class::Voltorb => 'x',
+      Class::One => '.',
+      Class::Uncertain => '-'
     }
-    num_voltorbs_string.push('\n');
   }
-  //println!("{}",num_voltorbs_string);
+}
 
-  // print the number of multipliers
-  //println!("The number of possible tables that have a multiplier in each cell:\n");
-  let mut num_multipliers_string = String::new();
-  for row in 0..SIZE {
-    for column in 0..SIZE
-    {
-      // if the game board spot is filled, don't add anything
-      match game_board[row][column] {
-        None =>
-        {
-          num_multipliers_string.push_str(format!("{: >4}", num_multipliers[row][column]).as_str());
-        }
-        Some(_) =>
-        {
-          num_multipliers_string.push_str(format!("{: >4}", '-').as_str());
-        }
-      }
-      num_multipliers_string.push(' ');
+// the key to the glyphs drawn over unopened cells
+pub const LEGEND: &str = "+ safe  x voltorb  . one  - unsure";
+
+// count the solutions and recommend a cell, weighting them by the level's prior if given
+pub fn aggregate(puzzle: &Puzzle, level: Option<u8>) -> Aggregate
+{
+  let mut aggregate = count(puzzle, level);
+
+  // recommend the unopened multiplier cell least likely to be a voltorb
+  aggregate.recommendation = vfstrategy::best(&vfstrategy::Greedy, puzzle, level, &aggregate);
+  aggregate
+}
+
+// every solution of the puzzle the level's prior allows, with its weight
+pub fn distribution(puzzle: &Puzzle, level: Option<u8>) -> Vec<(Board, f64)>
+{
+  let level = vflevel::prior(level, puzzle);
+  let mut solutions = Vec::new();
+  solve(puzzle, &mut |solution| {
+    let weight = vflevel::board_weight(level, solution);
+    if weight > 0.0 {
+      solutions.push((solution.clone(), weight));
     }
-    num_multipliers_string.push('\n');
+  });
+  solutions
+}
+
+// the coins won so far, the product of the revealed values or none before the first flip
+pub fn coins(board: &Board) -> u64
+{
+  let revealed: Vec<u8> = board.iter().flatten().flatten().copied().collect();
+  if revealed.is_empty() {
+    return 0;
   }
-  //println!("{}",num_multipliers_string);
+  revealed.iter().map(|&value| value as u64).product()
+}
 
+// the expected coins of quitting now against playing on optimally
+pub struct Advice {
+  pub quit: f64,
+  pub play: f64,
+  pub depth: usize
+}
+impl Advice
+{
+  // returns true if walking away is worth at least as much as playing on
+  pub fn should_quit(&self) -> bool {
+    self.quit >= self.play
+  }
+}
 
-  // for every cell, get its possible values
-  let mut possible_values: [[BTreeSet<u8>;SIZE];SIZE] = Default::default();
-  for row in 0..SIZE {
-    for column in 0..SIZE {
-      for board in boards
-      {
-        // add the cell value to the set
-        match board[row][column] {
-          None => {}
-          Some(value) =>
-          {
-            possible_values[row][column].insert(value);
-          }
-        }
+// weighs quitting with the coins against playing on, within the budget
+pub fn advise(coins: u64, solutions: &[(Board, f64)], game_board: &Board, budget: vfsearch::Budget) -> Advice
+{
+  let deadline = Instant::now() + budget.time;
+  let mut advice = Advice {
+    quit: coins as f64,
+    play: coins as f64,
+    depth: 0
+  };
+
+  // the game only goes on if some multiplier is still hidden
+  let set: Vec<usize> = (0..solutions.len())
+    .filter(|&index| !vfsearch::cleared(&solutions[index].0, game_board))
+    .collect();
+  if set.is_empty() {
+    return advice;
+  }
+
+  // the first flip sets the coins rather than multiplying them
+  let base = coins.max(1) as f64;
+  let mut search = vfsearch::Search::new(vfsearch::Objective::Coins, solutions, deadline);
+  for depth in 1..=budget.depth.max(1)
+  {
+    match search.value(game_board, &set, base, depth) {
+      Some(play) => {
+        advice.play = play;
+        advice.depth = depth;
       }
+      None => { break; }
     }
   }
 
-  // print the possible values of every cell
-  //println!("The possible values of every cell:\n");
-  let mut possible_values_string = String::new();
-  for row in &possible_values {
-    for cell in row
+  advice
+}
+
+// prints the advice on one line
+pub fn print_advice(advice: &Advice) -> String {
+  format!(
+    "quit {:.1} vs play {:.1}, quit? {}",
+    advice.quit,
+    advice.play,
+    if advice.should_quit() { "yes" } else { "no" }
+  )
+}
+
+// prints the probability of a value in every unopened cell that might multiply
+pub fn print_probabilities(aggregate: &Aggregate, game_board: &Board, value: u8) -> String
+{
+  let mut print_string = String::new();
+  for row in 0..aggregate.rows() {
+    for column in 0..aggregate.columns()
     {
-      // create the string of the possible values
-      let mut values_string = String::new();
-      for value in cell {
-        values_string.push_str(value.to_string().as_str());
+      // if the game board spot is filled or can't multiply, don't add anything
+      if game_board[row][column].is_some() || !aggregate.may_multiply(row, column) {
+        print_string.push_str(format!("{: >4}", '-').as_str());
       }
-      possible_values_string.push_str(format!("{: >3}", values_string).as_str());
-      possible_values_string.push(' ');
-    }
-    possible_values_string.push('\n');
-  }
-  //println!("{}",possible_values_string);
-
-  // find the lowest value
-  let mut lowest = u32::MAX;
-  for row in 0..SIZE {
-    for column in 0..SIZE {
-      if game_board[row][column] == None &&
-        (possible_values[row][column].contains(&2) || possible_values[row][column].contains(&3)) &&
-        num_voltorbs[row][column] < lowest {
-          recommendation.0 = row;
-          recommendation.1 = column;
-          lowest = num_voltorbs[row][column];
+      else {
+        let percent = aggregate.probability(row, column, value) * 100.0;
+        print_string.push_str(format!("{: >3.0}%", percent).as_str());
       }
+      print_string.push(' ');
     }
+    print_string.push('\n');
   }
+  print_string
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+  use crate::vfgen;
 
-  // print the lowest board string
-  let mut recommendation_string = String::new();
-  for row in 0..SIZE {
-    for column in 0..SIZE {
-      if row == recommendation.0 && column == recommendation.1 {
-        recommendation_string.push_str("X ");
+  // counts and weighs the boards by trying every value the marks allow in every unopened cell
+  fn brute_force(puzzle: &Puzzle, level: Option<u8>) -> (u64, Vec<Vec<Vec<f64>>>)
+  {
+    let (rows, columns) = (puzzle.rows(), puzzle.columns());
+    let open: Vec<(usize, usize)> = (0..rows * columns)
+      .map(|index| (index / columns, index % columns))
+      .filter(|&(row, column)| puzzle.board[row][column].is_none())
+      .collect();
+    let mut board = puzzle.board.clone();
+    let mut num_solutions = 0;
+    let mut weights = vec![vec![vec![0.0; puzzle.max as usize + 1]; columns]; rows];
+
+    // the points and voltorbs of a filled line
+    fn label(values: impl Iterator<Item = u8>) -> Label {
+      values.fold((0, 0), |label, value| (label.0 + value, label.1 + (value == 0) as u8))
+    }
+
+    // fill the open cells in order, dropping a board once a filled row misses its header
+    fn fill(index: usize, open: &[(usize, usize)], puzzle: &Puzzle, board: &mut Board, visit: &mut dyn FnMut(&Board)) {
+      if index == open.len() {
+        visit(board);
+        return;
       }
-      else {
-        recommendation_string.push_str("- ");
+      let (row, column) = open[index];
+      let filled = open[index + 1..].iter().all(|&(other, _)| other != row);
+      for value in members(puzzle.memo[row][column] & full(puzzle.max)) {
+        board[row][column] = Some(value);
+        if !filled || label(board[row].iter().map(|value| value.unwrap_or(0))) == puzzle.right[row] {
+          fill(index + 1, open, puzzle, board, visit);
+        }
       }
+      board[row][column] = None;
     }
-    recommendation_string.push('\n');
+    fill(0, &open, puzzle, &mut board, &mut |board| {
+      let value = |row: usize, column: usize| board[row][column].unwrap_or(0);
+      let fits = (0..rows).all(|row| label((0..columns).map(|column| value(row, column))) == puzzle.right[row])
+        && (0..columns).all(|column| label((0..rows).map(|row| value(row, column))) == puzzle.bottom[column]);
+      if !fits {
+        return;
+      }
+      num_solutions += 1;
+      let weight = vflevel::board_weight(vflevel::prior(level, puzzle), board);
+      for row in 0..rows {
+        for column in 0..columns {
+          weights[row][column][value(row, column) as usize] += weight;
+        }
+      }
+    });
+    (num_solutions, weights)
   }
 
+  #[test]
+  fn count_agrees_with_brute_force()
+  {
+    for seed in 0..60
+    {
+      // deal a round, then open all but seven cells and mark most of those left
+      let level = (seed % 8) as u8 + 1;
+      let round = vfgen::generate(level, Some(seed)).unwrap();
+      let mut puzzle = round.puzzle.clone();
+      let mut rng = vfgen::Rng::new(seed);
+      let mut cells: Vec<(usize, usize)> = (0..SIZE * SIZE).map(|index| (index / SIZE, index % SIZE)).collect();
+      for index in (1..cells.len()).rev() {
+        cells.swap(index, rng.below(index + 1));
+      }
+      for &(row, column) in &cells[18..] {
+        puzzle.board[row][column] = round.solution[row][column];
+      }
+      for &(row, column) in &cells[..6] {
+        let value = round.solution[row][column].unwrap();
+        puzzle.memo[row][column] = single(value) | single(rng.below(MAX as usize + 1) as u8);
+      }
 
-  // return the num_voltorbs_string
-  (num_voltorbs_string, recommendation_string)
-}
\ No newline at end of file
+      // the counts must match exactly, and the weights up to rounding
+      for level in [None, Some(level)] {
+        let aggregate = count(&puzzle, level);
+        let (num_solutions, weights) = brute_force(&puzzle, level);
+        assert_eq!(aggregate.num_solutions, num_solutions, "seed {}", seed);
+        let total: f64 = weights[0][0].iter().sum();
+        assert!((aggregate.total_weight - total).abs() <= 1e-9 * total.max(1.0), "seed {}", seed);
+        for row in 0..SIZE {
+          for column in 0..SIZE {
+            for value in 0..=MAX as usize {
+              let difference = aggregate.weights[row][column][value] - weights[row][column][value];
+              assert!(difference.abs() <= 1e-9 * total.max(1.0), "seed {} cell {},{}", seed, row, column);
+            }
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn count_handles_headers_near_the_limit()
+  {
+    // the first column sums to 255 and the last three rows can hold 7s, so adding a row can pass it
+    let rows = 38;
+    let mut puzzle = Puzzle::new(rows, 2, MAX_VALUE);
+    puzzle.right = vec![(8, 0); rows];
+    puzzle.right[rows - 1] = (2, 1);
+    puzzle.bottom = vec![(255, 1), (43, 0)];
+    for row in 0..rows - 3 {
+      puzzle.board[row] = vec![Some(7), Some(1)];
+    }
+    let aggregate = count(&puzzle, None);
+    assert_eq!(aggregate.num_solutions, 5);
+
+    // sixty open rows that each sum to 8, with the first column at the limit, have more solutions than a u64 holds
+    let rows = 60;
+    let mut puzzle = Puzzle::new(rows, 2, MAX_VALUE);
+    puzzle.right = vec![(8, 0); rows];
+    puzzle.bottom = vec![(255, 0), (225, 0)];
+    let aggregate = count(&puzzle, None);
+    assert_eq!(aggregate.num_solutions, u64::MAX);
+    assert!(aggregate.total_weight > u64::MAX as f64);
+  }
+}
@@ -1,28 +1,326 @@
+use crate::{vflip::*, vfsearch::*, cmdui::*};
+use crate::error::*;
 
+// constants
+const SAFE_STYLE: Style = Style::fg(Color::Green).bold();
+const VOLTORB_STYLE: Style = Style::fg(Color::Red).bold();
+const ONE_STYLE: Style = Style::fg(Color::Blue);
+const BEST_STYLE: Style = Style::fg(Color::Black).on(Color::Yellow).bold().underline();
+const GRID_WIDTH: usize = 5;
+const SUMMARY_WIDTH: usize = 40;
+const SETTLE_WIDTH: usize = 3;
 
-use crate::{vflip::*, cmdui::*};
+// the color of a chance of flipping a voltorb, from safe to likely
+fn heat(probability: f64) -> Color {
+  if probability < 0.2 { Color::Green } else if probability < 0.5 { Color::Yellow } else { Color::Red }
+}
 
 
+// the board with its headers and memo marks, colored by what is certain
 pub struct BoardComponent
 {
-  board: Board,
-  right: Header,
-  bottom: Header,
-  display: Option<UIHandle>,
-  loc: Option<(usize,usize)>
+  puzzle: Puzzle,
+  aggregate: Option<Aggregate>,
+  best: Option<(usize,usize)>,
+  cursor: Option<(usize,usize)>,
+  hints: bool
 }
-
-
 impl BoardComponent
 {
   // make a new board component
-  pub fn new(right: Header, bottom: Header, board: Board) -> Self  {
+  pub fn new(puzzle: Puzzle) -> Self  {
     BoardComponent {
-      board,
-      right,
-      bottom,
-      display: None,
-      loc: None
+      puzzle,
+      aggregate: None,
+      best: None,
+      cursor: None,
+      hints: true
+    }
+  }
+
+  // shows the puzzle with what the solver found and the flip it recommends
+  pub fn update(&mut self, puzzle: &Puzzle, aggregate: Aggregate, best: Option<(usize,usize)>)
+  {
+    self.puzzle = puzzle.clone();
+    self.aggregate = Some(aggregate);
+    self.best = best;
+
+    // keep the cursor on the board if it shrank
+    if let Some(cursor) = self.cursor {
+      self.cursor = Some((cursor.0.min(puzzle.rows() - 1), cursor.1.min(puzzle.columns() - 1)));
+    }
+  }
+
+  // shows a puzzle the solver hasn't seen, without its marks
+  pub fn show(&mut self, puzzle: &Puzzle) {
+    self.puzzle = puzzle.clone();
+    self.aggregate = None;
+    self.best = None;
+  }
+
+  // puts a cursor on the board for the arrows to move, or takes it away
+  pub fn show_cursor(&mut self, show: bool) {
+    self.cursor = if show { Some(self.cursor.unwrap_or((0, 0))) } else { None };
+  }
+
+  // shows what the solver found and recommends, or only what is revealed
+  pub fn show_hints(&mut self, show: bool) {
+    self.hints = show;
+  }
+
+  // the aggregate to draw, if hints are shown
+  fn hint(&self) -> Option<&Aggregate> {
+    self.aggregate.as_ref().filter(|_| self.hints)
+  }
+
+  // the cell under the cursor
+  pub fn cursor(&self) -> Option<(usize,usize)> {
+    self.cursor
+  }
+
+  // where a cell is drawn within the region
+  pub fn cell_loc(&self, region: Rect, row: usize, column: usize) -> (usize,usize) {
+    (region.row + row, region.column + 1 + 2 * column)
+  }
+
+  // the char and style of a cell
+  fn cell(&self, row: usize, column: usize) -> (char, Style)
+  {
+    let (c, style) = match (self.puzzle.board[row][column], self.hint()) {
+      (Some(0), _) => ('0', VOLTORB_STYLE),
+      (Some(value), _) => (value_char(value), Style::PLAIN),
+      (None, None) => ('-', Style::PLAIN),
+      (None, Some(aggregate)) =>
+      {
+        let class = aggregate.classify(row, column);
+        (class.glyph(), match class {
+          Class::Safe => SAFE_STYLE,
+          Class::Voltorb => VOLTORB_STYLE,
+          Class::One => ONE_STYLE,
+          Class::Uncertain => Style::PLAIN
+        })
+      }
+    };
+    (c, if self.hints && self.best == Some((row, column)) { BEST_STYLE } else { style })
+  }
+}
+impl Component for BoardComponent
+{
+  // the board, the headers, a line of memo marks and the legend
+  fn size(&self) -> (usize, usize) {
+    (self.puzzle.rows() + 5, (2 * self.puzzle.columns() + 8).max(LEGEND.len()))
+  }
+
+  // draws the board, then colors each cell
+  fn render(&self, display: &UIHandle, region: Rect) -> Result<()>
+  {
+    display.render(region.loc(), print_with_headers(&self.puzzle, self.hint()))?;
+    display.render((region.row + self.puzzle.rows() + 4, region.column), LEGEND.to_owned())?;
+    for row in 0..self.puzzle.rows() {
+      for column in 0..self.puzzle.columns() {
+        let (c, style) = self.cell(row, column);
+        display.render(self.cell_loc(region, row, column), Span::new(c.to_string(), style))?;
+      }
+    }
+    Ok(())
+  }
+
+  // moves the cursor with the arrows, if it's shown
+  fn handle_key(&mut self, key: Key) -> bool
+  {
+    let cursor = match self.cursor {
+      Some(cursor) => cursor,
+      None => { return false; }
+    };
+    self.cursor = Some(match key {
+      Key::Up => (cursor.0.saturating_sub(1), cursor.1),
+      Key::Down => ((cursor.0 + 1).min(self.puzzle.rows() - 1), cursor.1),
+      Key::Left => (cursor.0, cursor.1.saturating_sub(1)),
+      Key::Right => (cursor.0, (cursor.1 + 1).min(self.puzzle.columns() - 1)),
+      _ => { return false; }
+    });
+    true
+  }
+}
+
+
+// the chance of a voltorb in each cell that might multiply, as a heat map, with lines of summary below
+// and, if asked, the cells each flip would settle to its right
+pub struct ProbabilityComponent
+{
+  probabilities: Vec<Vec<Option<f64>>>,
+  settled: Option<Vec<Vec<Option<f64>>>>,
+  summary: Vec<String>
+}
+impl ProbabilityComponent
+{
+  // make an empty grid of a size
+  pub fn new(rows: usize, columns: usize) -> Self {
+    ProbabilityComponent {
+      probabilities: vec![vec![None; columns]; rows],
+      settled: None,
+      summary: Vec::new()
     }
   }
-}
\ No newline at end of file
+
+  // shows the cells each flip would settle next to the heat map, or hides them
+  pub fn overlay(&mut self, settled: Option<Vec<Vec<Option<f64>>>>) {
+    self.settled = settled;
+  }
+
+  // the width of the heat map
+  fn grid_width(&self) -> usize {
+    GRID_WIDTH * self.probabilities.first().map_or(0, |row| row.len())
+  }
+
+  // the width of the settled cells, narrower than the heat map so both fit beside the board
+  fn settle_width(&self) -> usize {
+    self.settled.as_ref().map_or(0, |settled| SETTLE_WIDTH * settled.first().map_or(0, |row| row.len()))
+  }
+
+  // shows the probabilities of the aggregate for the unopened cells of the board
+  pub fn update(&mut self, aggregate: &Aggregate, game_board: &Board, summary: Vec<String>)
+  {
+    self.probabilities = (0..aggregate.rows()).map(|row| {
+      (0..aggregate.columns()).map(|column| {
+        if game_board[row][column].is_none() && aggregate.may_multiply(row, column) {
+          Some(aggregate.probability(row, column, 0))
+        }
+        else {
+          None
+        }
+      }).collect()
+    }).collect();
+    self.summary = summary;
+  }
+}
+impl Component for ProbabilityComponent
+{
+  // the grids, a blank line and the summary
+  fn size(&self) -> (usize, usize) {
+    (self.probabilities.len() + 1 + self.summary.len(), (self.grid_width() + self.settle_width()).max(SUMMARY_WIDTH) + 1)
+  }
+
+  // draws each percentage in the color of its risk
+  fn render(&self, display: &UIHandle, region: Rect) -> Result<()>
+  {
+    for (row, probabilities) in self.probabilities.iter().enumerate() {
+      for (column, probability) in probabilities.iter().enumerate() {
+        let loc = (region.row + row, region.column + GRID_WIDTH * column);
+        match probability {
+          Some(probability) =>
+          {
+            let text = format!("{: >3.0}%", probability * 100.0);
+            display.render(loc, Span::new(text, Style::fg(heat(*probability))))?;
+          }
+          None => { display.render(loc, format!("{: >4}", '-'))?; }
+        }
+      }
+    }
+    if let Some(settled) = &self.settled {
+      for (row, cells) in settled.iter().enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+          let loc = (region.row + row, region.column + self.grid_width() + SETTLE_WIDTH * column);
+          match cell {
+            Some(cell) => { display.render(loc, format!("{: >3.0}", cell))?; }
+            None => { display.render(loc, format!("{: >3}", '-'))?; }
+          }
+        }
+      }
+    }
+    for (index, line) in self.summary.iter().enumerate() {
+      display.render((region.row + self.probabilities.len() + 1 + index, region.column + 1), line.clone())?;
+    }
+    Ok(())
+  }
+}
+
+
+// the chance of clearing the board after flipping each cell, and how far the search looked
+pub struct RecommendationComponent
+{
+  decision: Option<Decision>,
+  rows: usize,
+  columns: usize,
+  summary: String
+}
+impl RecommendationComponent
+{
+  // make an empty grid of a size
+  pub fn new(rows: usize, columns: usize) -> Self {
+    RecommendationComponent {
+      decision: None,
+      rows,
+      columns,
+      summary: String::new()
+    }
+  }
+
+  // shows a decision, with a line about the search below
+  pub fn update(&mut self, decision: Decision, summary: String) {
+    self.rows = decision.win.len();
+    self.columns = decision.win.first().map_or(0, |row| row.len());
+    self.decision = Some(decision);
+    self.summary = summary;
+  }
+}
+impl Component for RecommendationComponent
+{
+  // the grid and the summary
+  fn size(&self) -> (usize, usize) {
+    (self.rows + 1, (GRID_WIDTH * self.columns).max(self.summary.len()) + 1)
+  }
+
+  // draws the grid starring the best cell
+  fn render(&self, display: &UIHandle, region: Rect) -> Result<()>
+  {
+    if let Some(decision) = &self.decision {
+      display.render(region.loc(), print_decision(decision))?;
+    }
+    display.render((region.row + self.rows, region.column + 1), self.summary.clone())
+  }
+}
+
+
+// a board the user is asked about before it replaces theirs
+pub struct PreviewComponent
+{
+  board: Option<Board>,
+  rows: usize,
+  columns: usize
+}
+impl PreviewComponent
+{
+  // make an empty preview of a size
+  pub fn new(rows: usize, columns: usize) -> Self {
+    PreviewComponent {
+      board: None,
+      rows,
+      columns
+    }
+  }
+
+  // shows a board, or nothing
+  pub fn show(&mut self, board: Option<&Board>) {
+    if let Some(board) = board {
+      self.rows = board.len();
+      self.columns = board.first().map_or(0, |row| row.len());
+    }
+    self.board = board.cloned();
+  }
+}
+impl Component for PreviewComponent
+{
+  // the board alone
+  fn size(&self) -> (usize, usize) {
+    (self.rows, 2 * self.columns + 1)
+  }
+
+  // draws the board if there is one
+  fn render(&self, display: &UIHandle, region: Rect) -> Result<()> {
+    match &self.board {
+      Some(board) => display.render(region.loc(), print(board)),
+      None => Ok(())
+    }
+  }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use crate::vflip::*;
+
+// constants
+pub const MAX_SOLUTIONS: u64 = 20000;
+pub const DEFAULT_BUDGET: Budget = Budget { depth: 4, time: Duration::from_millis(300) };
+
+// how far ahead, and for how long, the search may look
+#[derive(Clone, Copy)]
+pub struct Budget {
+  pub depth: usize,
+  pub time: Duration
+}
+
+// what a search tries to maximise
+#[derive(Clone, Copy, PartialEq)]
+pub enum Objective {
+  Clear,
+  Coins
+}
+
+// the chance of clearing the board after flipping each cell
+pub struct Decision {
+  pub win: Vec<Vec<Option<f64>>>,
+  pub best: Option<(usize, usize)>,
+  pub depth: usize
+}
+impl Decision
+{
+  // a decision that searched nothing, with the flip chosen some other way
+  pub fn unsearched(board: &Board, best: Option<(usize, usize)>) -> Self {
+    Decision {
+      win: board.iter().map(|row| vec![None; row.len()]).collect(),
+      best,
+      depth: 0
+    }
+  }
+}
+
+// returns true if every multiplier of the solution is revealed on the board
+pub fn cleared(solution: &Board, board: &Board) -> bool
+{
+  for row in 0..board.len() {
+    for column in 0..board[row].len() {
+      if board[row][column].is_none() && solution[row][column].is_some_and(|value| value > 1) {
+        return false;
+      }
+    }
+  }
+  true
+}
+
+// the state shared by every node of a search
+pub struct Search<'a> {
+  objective: Objective,
+  solutions: &'a [(Board, f64)],
+  deadline: Instant,
+  memo: HashMap<(Board, usize), f64>
+}
+impl<'a> Search<'a>
+{
+  // starts a search over weighted solutions that must finish by the deadline
+  pub fn new(objective: Objective, solutions: &'a [(Board, f64)], deadline: Instant) -> Self {
+    Search {
+      objective,
+      solutions,
+      deadline,
+      memo: HashMap::new()
+    }
+  }
+
+  // what stopping is worth with the given coins
+  fn payoff(&self, coins: f64, cleared: bool) -> f64 {
+    match self.objective {
+      Objective::Clear => if cleared { 1.0 } else { 0.0 },
+      Objective::Coins => coins
+    }
+  }
+
+  // the total weight of a set of solutions
+  fn weight(&self, set: &[usize]) -> f64 {
+    set.iter().map(|&index| self.solutions[index].1).sum()
+  }
+
+  // the unopened cells worth flipping, which aren't certainly a voltorb or certainly a one
+  fn candidates(&self, board: &Board, set: &[usize]) -> Vec<(usize, usize)>
+  {
+    let mut candidates = Vec::new();
+    for row in 0..board.len() {
+      for column in 0..board[row].len() {
+        if board[row][column].is_none()
+        {
+          let mut seen = [false; MAX_VALUE as usize + 1];
+          for &index in set {
+            if let Some(value) = self.solutions[index].0[row][column] {
+              seen[value as usize] = true;
+            }
+          }
+          if seen[2..].contains(&true) || (seen[0] && seen[1]) {
+            candidates.push((row, column));
+          }
+        }
+      }
+    }
+    candidates
+  }
+
+  // estimates the payoff by charging each solution the risk of every multiplier it still hides
+  fn horizon(&self, board: &Board, set: &[usize], coins: f64) -> f64
+  {
+    // the chance that each cell is safe to flip
+    let total = self.weight(set);
+    let mut safe: Vec<Vec<f64>> = board.iter().map(|row| vec![0.0; row.len()]).collect();
+    for &(ref solution, weight) in set.iter().map(|&index| &self.solutions[index]) {
+      for row in 0..board.len() {
+        for column in 0..board[row].len() {
+          if solution[row][column] != Some(0) {
+            safe[row][column] += weight / total;
+          }
+        }
+      }
+    }
+
+    // the solutions survive flipping their hidden multipliers independently
+    let mut estimate = 0.0;
+    for &(ref solution, weight) in set.iter().map(|&index| &self.solutions[index])
+    {
+      let mut survive = weight / total;
+      let mut cleared_coins = coins;
+      for row in 0..board.len() {
+        for column in 0..board[row].len() {
+          match (board[row][column], solution[row][column]) {
+            (None, Some(value)) if value > 1 =>
+            {
+              survive *= safe[row][column];
+              cleared_coins *= value as f64;
+            }
+            _ => {}
+          }
+        }
+      }
+      estimate += survive * self.payoff(cleared_coins, true);
+    }
+    estimate.max(self.payoff(coins, false))
+  }
+
+  // the payoff of a board no solution of the set has cleared yet, None when out of time
+  pub fn value(&mut self, board: &Board, set: &[usize], coins: f64, depth: usize) -> Option<f64>
+  {
+    // estimate the payoff at the horizon
+    if depth == 0 {
+      return Some(self.horizon(board, set, coins));
+    }
+
+    // check the memo
+    if let Some(&value) = self.memo.get(&(board.clone(), depth)) {
+      return Some(value);
+    }
+
+    // take the best flip, or stop
+    let mut best = self.payoff(coins, false);
+    for cell in self.candidates(board, set) {
+      let value = self.flip(board, set, coins, cell, depth)?;
+      if value > best {
+        best = value;
+      }
+    }
+
+    self.memo.insert((board.clone(), depth), best);
+    Some(best)
+  }
+
+  // the payoff of flipping a cell next, None when out of time
+  pub fn flip(&mut self, board: &Board, set: &[usize], coins: f64, cell: (usize, usize), depth: usize) -> Option<f64>
+  {
+    // stop if the budget has run out
+    if Instant::now() > self.deadline {
+      return None;
+    }
+
+    // split the solutions by the value they reveal
+    let mut parts: Vec<Vec<usize>> = vec![Vec::new(); MAX_VALUE as usize + 1];
+    for &index in set {
+      if let Some(value) = self.solutions[index].0[cell.0][cell.1] {
+        parts[value as usize].push(index);
+      }
+    }
+
+    // every value but a voltorb either clears the board or leads to another decision
+    let total = self.weight(set);
+    let mut payoff = 0.0;
+    for value in (1..=MAX_VALUE).filter(|&value| !parts[value as usize].is_empty())
+    {
+      let mut next = board.clone();
+      next[cell.0][cell.1] = Some(value);
+      let next_coins = coins * value as f64;
+      let (done, rest): (Vec<usize>, Vec<usize>) = parts[value as usize]
+        .iter()
+        .partition(|&&index| cleared(&self.solutions[index].0, &next));
+      payoff += self.weight(&done) / total * self.payoff(next_coins, true);
+      if !rest.is_empty() {
+        payoff += self.weight(&rest) / total * self.value(&next, &rest, next_coins, depth - 1)?;
+      }
+    }
+
+    Some(payoff)
+  }
+}
+
+// the weighted solutions of the puzzle, or none if there are too many to search
+pub fn solutions(puzzle: &Puzzle, level: Option<u8>, aggregate: &Aggregate) -> Vec<(Board, f64)>
+{
+  if aggregate.num_solutions > MAX_SOLUTIONS {
+    return Vec::new();
+  }
+  distribution(puzzle, level)
+}
+
+// chooses the flip most likely to clear the board under optimal play, within the budget,
+// from the puzzle's aggregate and its solutions
+pub fn decide(puzzle: &Puzzle, aggregate: &Aggregate, solutions: &[(Board, f64)], budget: Budget) -> Decision
+{
+  let board = &puzzle.board;
+  let deadline = Instant::now() + budget.time;
+  let mut decision = Decision::unsearched(board, None);
+
+  // too many solutions to search, so fall back on the greedy flip
+  if aggregate.num_solutions > MAX_SOLUTIONS {
+    decision.best = aggregate.recommendation;
+    return decision;
+  }
+
+  // the solutions the game can still be in, since it ends once every multiplier is found
+  let solutions: Vec<(Board, f64)> = solutions
+    .iter()
+    .filter(|(solution, _)| !cleared(solution, board))
+    .cloned()
+    .collect();
+  let set: Vec<usize> = (0..solutions.len()).collect();
+  let mut search = Search::new(Objective::Clear, &solutions, deadline);
+
+  // deepen the search while the budget allows
+  let candidates = search.candidates(board, &set);
+  'deepen: for depth in 1..=budget.depth.max(1)
+  {
+    let mut win = Decision::unsearched(board, None).win;
+    for &(row, column) in &candidates {
+      match search.flip(board, &set, 1.0, (row, column), depth) {
+        Some(value) => { win[row][column] = Some(value); }
+        None => { break 'deepen; }
+      }
+    }
+    decision.win = win;
+    decision.depth = depth;
+  }
+
+  // recommend the best cell, or the safest multiplier if even one flip was too slow
+  let mut best = f64::MIN;
+  for row in 0..decision.win.len() {
+    for column in 0..decision.win[row].len() {
+      if let Some(value) = decision.win[row][column] {
+        if value > best {
+          decision.best = Some((row, column));
+          best = value;
+        }
+      }
+    }
+  }
+  if decision.depth == 0 {
+    decision.best = aggregate.recommendation;
+  }
+
+  decision
+}
+
+// prints the chance of clearing the board after flipping each cell, starring the best
+pub fn print_decision(decision: &Decision) -> String
+{
+  let mut print_string = String::new();
+  for row in 0..decision.win.len() {
+    for column in 0..decision.win[row].len()
+    {
+      // mark the recommended cell
+      print_string.push(if decision.best == Some((row, column)) { '*' } else { ' ' });
+      match decision.win[row][column] {
+        Some(win) => { print_string.push_str(format!("{: >3.0}%", win * 100.0).as_str()); }
+        None => { print_string.push_str(format!("{: >4}", '-').as_str()); }
+      }
+    }
+    print_string.push('\n');
+  }
+  print_string
+}
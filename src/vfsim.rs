@@ -0,0 +1,119 @@
+use crate::error::*;
+use crate::{vfgen, vfsearch};
+use crate::vflip::*;
+use crate::vfstrategy::{self, Strategy};
+
+// how a strategy did over the rounds of a level
+pub struct Report {
+  pub level: u8,
+  pub rounds: u64,
+  pub wins: u64,
+  pub coins: u64,
+  pub flips: u64,
+  pub losses: Vec<u64>
+}
+impl Report
+{
+  // the share of the rounds won
+  pub fn win_rate(&self) -> f64 {
+    self.wins as f64 / self.rounds.max(1) as f64
+  }
+
+  // the coins won in a round on average, counting losses as none
+  pub fn average_coins(&self) -> f64 {
+    self.coins as f64 / self.rounds.max(1) as f64
+  }
+
+  // the flips made in a round on average
+  pub fn average_flips(&self) -> f64 {
+    self.flips as f64 / self.rounds.max(1) as f64
+  }
+}
+
+// how a single round ended
+enum Outcome {
+  Won(u64),
+  Lost,
+  Quit(u64)
+}
+
+// plays the rounds of a level dealt from consecutive seeds, reporting how the strategy did
+pub fn simulate(level: u8, rounds: u64, seed: u64, strategy: &dyn Strategy) -> Result<Report>
+{
+  let mut report = Report {
+    level,
+    rounds,
+    wins: 0,
+    coins: 0,
+    flips: 0,
+    losses: Vec::new()
+  };
+  for index in 0..rounds
+  {
+    let round = vfgen::generate(level, Some(seed.wrapping_add(index)))?;
+    let (outcome, flips) = play(&round, strategy);
+    report.flips += flips as u64;
+    match outcome
+    {
+      Outcome::Won(coins) => {
+        report.wins += 1;
+        report.coins += coins;
+      }
+
+      // count the losses by the flip that found the voltorb
+      Outcome::Lost => {
+        if report.losses.len() < flips {
+          report.losses.resize(flips, 0);
+        }
+        report.losses[flips - 1] += 1;
+      }
+      Outcome::Quit(coins) => { report.coins += coins; }
+    }
+  }
+  Ok(report)
+}
+
+// plays a round until a voltorb, the last multiplier or nothing worth flipping, returning how it ended and the flips it took
+fn play(round: &vfgen::Round, strategy: &dyn Strategy) -> (Outcome, usize)
+{
+  let mut puzzle = round.puzzle.clone();
+  let mut flips = 0;
+  loop
+  {
+    if vfsearch::cleared(&round.solution, &puzzle.board) {
+      return (Outcome::Won(coins(&puzzle.board)), flips);
+    }
+    let level = Some(round.level);
+    let (row, column) = match vfstrategy::best(strategy, &puzzle, level, &aggregate(&puzzle, level)) {
+      Some(cell) if puzzle.board[cell.0][cell.1].is_none() => cell,
+      _ => { return (Outcome::Quit(coins(&puzzle.board)), flips); }
+    };
+    flips += 1;
+    puzzle.board[row][column] = round.solution[row][column];
+    if puzzle.board[row][column] == Some(0) {
+      return (Outcome::Lost, flips);
+    }
+  }
+}
+
+// prints the report, with the losses by the flip they came on
+pub fn print_report(report: &Report) -> String
+{
+  let mut print_string = format!(
+    "level {}: {} rounds, won {} ({:.1}%), {:.1} coins and {:.1} flips on average\n",
+    report.level,
+    report.rounds,
+    report.wins,
+    report.win_rate() * 100.0,
+    report.average_coins(),
+    report.average_flips()
+  );
+  print_string.push_str("  lost on flip:");
+  for (index, &losses) in report.losses.iter().enumerate() {
+    if losses > 0 {
+      print_string.push_str(format!(" {}:{}", index+1, losses).as_str());
+    }
+  }
+  print_string.push('\n');
+  print_string
+}
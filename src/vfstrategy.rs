@@ -0,0 +1,288 @@
+use crate::error::*;
+use crate::vfsearch;
+use crate::vflip::*;
+
+// the names of the strategies, the first being the default
+pub const NAMES: [&str; 6] = ["search", "greedy", "safest", "gain", "info", "settle"];
+
+// cells with their scores, best first
+pub type Ranking = Vec<((usize, usize), f64)>;
+
+// a way of ranking the cells to flip
+pub trait Strategy {
+  fn rank(&self, puzzle: &Puzzle, level: Option<u8>, aggregate: &Aggregate) -> Ranking;
+}
+
+// the strategy with a name, searching within the budget
+pub fn by_name(name: &str, budget: vfsearch::Budget) -> Result<Box<dyn Strategy>>
+{
+  Ok(match name {
+    "search" => Box::new(Search(budget)),
+    "greedy" => Box::new(Greedy),
+    "safest" => Box::new(Safest),
+    "gain" => Box::new(Gain),
+    "info" => Box::new(Info),
+    "settle" => Box::new(Settle),
+    _ => { return Err(Error::new(format!("Unknown strategy {}; must be in {:?}", name, NAMES).as_str())); }
+  })
+}
+
+// the cells in the order the strategy would flip them
+pub fn rank(strategy: &dyn Strategy, puzzle: &Puzzle, level: Option<u8>, aggregate: &Aggregate) -> Ranking {
+  safe_first(strategy.rank(puzzle, level, aggregate), aggregate)
+}
+
+// the cell the strategy would flip
+pub fn best(strategy: &dyn Strategy, puzzle: &Puzzle, level: Option<u8>, aggregate: &Aggregate) -> Option<(usize, usize)> {
+  rank(strategy, puzzle, level, aggregate).first().map(|&(cell, _)| cell)
+}
+
+// moves the cells that may multiply but can't be voltorbs ahead of the rest, the likeliest multiplier first,
+// keeping the order of the rest
+fn safe_first(mut ranking: Ranking, aggregate: &Aggregate) -> Ranking
+{
+  let safe = |(row, column): (usize, usize)| aggregate.classify(row, column) == Class::Safe;
+  ranking.sort_by(|&(a, _), &(b, _)| match (safe(a), safe(b)) {
+    (true, true) => aggregate.multiplier(b.0, b.1).total_cmp(&aggregate.multiplier(a.0, a.1)),
+    (a, b) => b.cmp(&a)
+  });
+  ranking
+}
+
+// scores the unopened cells that pass the filter, ranking them best first, the earliest first on a tie
+fn rank_by<F, S>(puzzle: &Puzzle, filter: F, score: S) -> Ranking
+where
+  F: Fn(usize, usize) -> bool,
+  S: Fn(usize, usize) -> f64
+{
+  let mut ranking = Ranking::new();
+  for row in 0..puzzle.rows() {
+    for column in 0..puzzle.columns() {
+      if puzzle.board[row][column].is_none() && filter(row, column) {
+        ranking.push(((row, column), score(row, column)));
+      }
+    }
+  }
+  ranking.sort_by(|a, b| b.1.total_cmp(&a.1));
+  ranking
+}
+
+// the chance of clearing the board from each flip, searched within the budget
+pub struct Search(pub vfsearch::Budget);
+impl Strategy for Search
+{
+  // rank the flips of the decision
+  fn rank(&self, puzzle: &Puzzle, level: Option<u8>, aggregate: &Aggregate) -> Ranking {
+    let solutions = vfsearch::solutions(puzzle, level, aggregate);
+    Decided(&vfsearch::decide(puzzle, aggregate, &solutions, self.0)).rank(puzzle, level, aggregate)
+  }
+}
+
+// the flips of a search already made, for a caller that also shows the decision
+pub struct Decided<'a>(pub &'a vfsearch::Decision);
+impl Strategy for Decided<'_>
+{
+  // rank the flips of the decision
+  fn rank(&self, _puzzle: &Puzzle, _level: Option<u8>, _aggregate: &Aggregate) -> Ranking {
+    from_decision(self.0)
+  }
+}
+
+// ranks the flips of a decision by their chance of clearing, its best flip first however it was chosen
+fn from_decision(decision: &vfsearch::Decision) -> Ranking
+{
+  let mut ranking = Ranking::new();
+  for row in 0..decision.win.len() {
+    for column in 0..decision.win[row].len() {
+      if let Some(win) = decision.win[row][column] {
+        ranking.push(((row, column), win));
+      }
+    }
+  }
+  ranking.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+  // the search may have fallen back on a cell it didn't score
+  if let Some(best) = decision.best {
+    let index = ranking.iter().position(|&(cell, _)| cell == best);
+    let entry = index.map_or((best, 0.0), |index| ranking.remove(index));
+    ranking.insert(0, entry);
+  }
+  ranking
+}
+
+// the least likely voltorb among the cells that may multiply
+pub struct Greedy;
+impl Strategy for Greedy
+{
+  // rank the cells that may multiply by their safety
+  fn rank(&self, puzzle: &Puzzle, _level: Option<u8>, aggregate: &Aggregate) -> Ranking {
+    rank_by(
+      puzzle,
+      |row, column| aggregate.may_multiply(row, column),
+      |row, column| 1.0 - aggregate.probability(row, column, 0)
+    )
+  }
+}
+
+// the least likely voltorb among the cells that can still tell something
+pub struct Safest;
+impl Strategy for Safest
+{
+  // rank the cells that aren't certainly ones or voltorbs by their safety
+  fn rank(&self, puzzle: &Puzzle, _level: Option<u8>, aggregate: &Aggregate) -> Ranking {
+    rank_by(
+      puzzle,
+      |row, column| undecided(aggregate, row, column),
+      |row, column| 1.0 - aggregate.probability(row, column, 0)
+    )
+  }
+}
+
+// the highest expected multiplier of the coins
+pub struct Gain;
+impl Strategy for Gain
+{
+  // rank the cells that may multiply by the value they're expected to hold
+  fn rank(&self, puzzle: &Puzzle, _level: Option<u8>, aggregate: &Aggregate) -> Ranking {
+    rank_by(
+      puzzle,
+      |row, column| aggregate.may_multiply(row, column),
+      |row, column| puzzle.values().map(|value| value as f64 * aggregate.probability(row, column, value)).sum()
+    )
+  }
+}
+
+// the most uncertain cell, whose value tells the most about the board
+pub struct Info;
+impl Strategy for Info
+{
+  // rank the cells that aren't certainly ones or voltorbs by the entropy of their values
+  fn rank(&self, puzzle: &Puzzle, _level: Option<u8>, aggregate: &Aggregate) -> Ranking {
+    rank_by(
+      puzzle,
+      |row, column| undecided(aggregate, row, column),
+      |row, column| entropy(aggregate, row, column)
+    )
+  }
+}
+
+// the cell whose value, unless it's a voltorb, is expected to make the most cells certain
+pub struct Settle;
+impl Strategy for Settle
+{
+  // rank the cells that aren't certainly ones or voltorbs by the cells they settle,
+  // or by their entropy if there are too many solutions to weigh, each weighted by their safety
+  fn rank(&self, puzzle: &Puzzle, level: Option<u8>, aggregate: &Aggregate) -> Ranking
+  {
+    let safety = |row: usize, column: usize| 1.0 - aggregate.probability(row, column, 0);
+    match settled(puzzle, aggregate, &vfsearch::solutions(puzzle, level, aggregate)) {
+      Some(grid) => rank_by(
+        puzzle,
+        |row, column| undecided(aggregate, row, column),
+        |row, column| safety(row, column) * (1.0 + grid[row][column].unwrap_or(0.0))
+      ),
+      None => rank_by(
+        puzzle,
+        |row, column| undecided(aggregate, row, column),
+        |row, column| safety(row, column) * (1.0 + entropy(aggregate, row, column))
+      )
+    }
+  }
+}
+
+// the other unknown cells flipping each unknown cell is expected to make certain, a voltorb settling none,
+// from the puzzle's solutions, or none if there are too many solutions to weigh
+pub fn settled(puzzle: &Puzzle, aggregate: &Aggregate, solutions: &[(Board, f64)]) -> Option<Vec<Vec<Option<f64>>>>
+{
+  if aggregate.num_solutions > vfsearch::MAX_SOLUTIONS || aggregate.total_weight <= 0.0 {
+    return None;
+  }
+  let total: f64 = solutions.iter().map(|(_, weight)| weight).sum();
+
+  // the unopened cells that could hold more than one value
+  let mut unknown = Vec::new();
+  for row in 0..puzzle.rows() {
+    for column in 0..puzzle.columns() {
+      let values = puzzle.values().filter(|&value| aggregate.possible(row, column, value)).count();
+      if puzzle.board[row][column].is_none() && values > 1 {
+        unknown.push((row, column));
+      }
+    }
+  }
+
+  // for each value the cell may show, the cells every solution showing it agrees on
+  let mut grid = vec![vec![None; puzzle.columns()]; puzzle.rows()];
+  for &(row, column) in &unknown
+  {
+    let mut expected = 0.0;
+    for value in 1..=puzzle.max
+    {
+      let group: Vec<&(Board, f64)> = solutions
+        .iter()
+        .filter(|(solution, _)| solution[row][column] == Some(value))
+        .collect();
+      let first = match group.first() {
+        Some((first, _)) => first,
+        None => { continue; }
+      };
+      let weight: f64 = group.iter().map(|(_, weight)| weight).sum();
+      let certain = unknown
+        .iter()
+        .filter(|&&cell| cell != (row, column))
+        .filter(|&&(other_row, other_column)| {
+          group.iter().all(|(solution, _)| solution[other_row][other_column] == first[other_row][other_column])
+        })
+        .count();
+      expected += weight / total * certain as f64;
+    }
+    grid[row][column] = Some(expected);
+  }
+  Some(grid)
+}
+
+// returns true if flipping the cell could still win or teach something
+fn undecided(aggregate: &Aggregate, row: usize, column: usize) -> bool {
+  !matches!(aggregate.classify(row, column), Class::One | Class::Voltorb)
+}
+
+// the bits of entropy in the value of a cell
+pub fn entropy(aggregate: &Aggregate, row: usize, column: usize) -> f64
+{
+  let values = 0..aggregate.weights[row][column].len() as u8;
+  values
+    .map(|value| aggregate.probability(row, column, value))
+    .filter(|&probability| probability > 0.0)
+    .map(|probability| -probability * probability.log2())
+    .sum()
+}
+
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  // a 3x3 board of ones and twos with three solutions
+  fn puzzle() -> Puzzle
+  {
+    let mut puzzle = Puzzle::new(3, 3, 2);
+    puzzle.right = vec![(5, 0), (4, 1), (3, 1)];
+    puzzle.bottom = vec![(4, 1), (3, 1), (5, 0)];
+    puzzle
+  }
+
+  #[test]
+  fn settle_prefers_a_safe_cell_to_a_risky_one_settling_more()
+  {
+    let puzzle = puzzle();
+    let aggregate = aggregate(&puzzle, None);
+    assert_eq!(aggregate.num_solutions, 3);
+
+    // the middle of the bottom row settles more when it isn't a voltorb, but it is one in a third of the solutions
+    assert_eq!(aggregate.probability(0, 1, 0), 0.0);
+    assert!(aggregate.probability(2, 1, 0) > 0.3);
+    let ranking = Settle.rank(&puzzle, None, &aggregate);
+    let position = |cell: (usize, usize)| ranking.iter().position(|&(other, _)| other == cell).unwrap();
+    assert!(position((0, 1)) < position((2, 1)));
+  }
+}